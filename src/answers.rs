@@ -0,0 +1,273 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "answers.txt";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    Right,
+    TooHigh,
+    TooLow,
+}
+
+impl TryFrom<&str> for Status {
+    type Error = String;
+
+    fn try_from(raw: &str) -> Result<Self, Self::Error> {
+        match raw {
+            "right" => Ok(Self::Right),
+            "too-high" => Ok(Self::TooHigh),
+            "too-low" => Ok(Self::TooLow),
+            _ => Err(format!("Unrecognized status: {}", raw)),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Right => "right",
+            Self::TooHigh => "too-high",
+            Self::TooLow => "too-low",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+    pub day: u8,
+    pub part: u8,
+    pub answer: String,
+    pub status: Status,
+}
+
+impl TryFrom<&str> for Entry {
+    type Error = String;
+
+    // example line -> `3 1 too-low 1234`
+    fn try_from(raw: &str) -> Result<Self, Self::Error> {
+        let tokens: Vec<&str> = raw.splitn(4, ' ').collect();
+        if tokens.len() != 4 {
+            return Err(format!("Malformed entry: {}", raw));
+        }
+
+        Ok(Self {
+            day: tokens[0]
+                .parse()
+                .map_err(|_| format!("Invalid day: {}", tokens[0]))?,
+            part: tokens[1]
+                .parse()
+                .map_err(|_| format!("Invalid part: {}", tokens[1]))?,
+            status: Status::try_from(tokens[2])?,
+            answer: tokens[3].to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.day, self.part, self.status, self.answer
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Warning {
+    KnownWrong(Status),
+    NotBelow(String),
+    NotAbove(String),
+    DiffersFromAccepted(String),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KnownWrong(status) => write!(f, "already submitted and it was {}", status),
+            Self::NotBelow(bound) => write!(f, "should be lower than {} (too high)", bound),
+            Self::NotAbove(bound) => write!(f, "should be higher than {} (too low)", bound),
+            Self::DiffersFromAccepted(answer) => write!(f, "accepted answer was {}", answer),
+        }
+    }
+}
+
+/// Answers submitted to the website, along with their verdict. They are
+/// stored as plain text, one entry per line, and `#` starts a comment.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AnswerStore {
+    pub entries: Vec<Entry>,
+}
+
+impl TryFrom<&str> for AnswerStore {
+    type Error = String;
+
+    fn try_from(raw: &str) -> Result<Self, Self::Error> {
+        let entries = raw
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(Entry::try_from)
+            .collect::<Result<Vec<Entry>, String>>()?;
+
+        Ok(Self { entries })
+    }
+}
+
+impl fmt::Display for AnswerStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# day part status answer")?;
+        for entry in self.entries.iter() {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl AnswerStore {
+    /// Loads the store from a file. A missing file is an empty store.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+
+        Self::try_from(raw.as_str()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Records the verdict for an answer, replacing any previous one. The
+    /// answer must fit on a single line of the file, so it can't be empty,
+    /// span several lines or start or end with whitespace.
+    pub fn record(
+        &mut self,
+        day: u8,
+        part: u8,
+        answer: &str,
+        status: Status,
+    ) -> Result<(), String> {
+        if answer.is_empty() || answer.trim() != answer || answer.contains(['\n', '\r']) {
+            return Err(format!("Invalid answer: {:?}", answer));
+        }
+
+        self.entries
+            .retain(|x| !(x.day == day && x.part == part && x.answer == answer));
+        self.entries.push(Entry {
+            day,
+            part,
+            answer: answer.to_string(),
+            status,
+        });
+        Ok(())
+    }
+
+    /// Checks a computed answer against the submitted ones. Bounds only
+    /// apply when both answers are numbers.
+    pub fn check(&self, day: u8, part: u8, answer: &str) -> Vec<Warning> {
+        let mut warnings = vec![];
+        let entries = self
+            .entries
+            .iter()
+            .filter(|x| x.day == day && x.part == part);
+        let value = answer.parse::<i128>().ok();
+
+        for entry in entries {
+            if entry.answer == answer {
+                if entry.status != Status::Right {
+                    warnings.push(Warning::KnownWrong(entry.status));
+                }
+                continue;
+            }
+
+            let bound = entry.answer.parse::<i128>().ok();
+            match (entry.status, value, bound) {
+                (Status::Right, _, _) => {
+                    warnings.push(Warning::DiffersFromAccepted(entry.answer.to_owned()))
+                }
+                (Status::TooHigh, Some(value), Some(bound)) if value > bound => {
+                    warnings.push(Warning::NotBelow(entry.answer.to_owned()))
+                }
+                (Status::TooLow, Some(value), Some(bound)) if value < bound => {
+                    warnings.push(Warning::NotAbove(entry.answer.to_owned()))
+                }
+                _ => {}
+            }
+        }
+
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORE: &str =
+        "# day part status answer\n1 1 too-low 100\n1 1 too-high 200\n1 2 right 42\n";
+
+    #[test]
+    fn test_answers_parse() {
+        let store = AnswerStore::try_from(STORE).unwrap();
+
+        assert_eq!(store.entries.len(), 3);
+        assert_eq!(
+            store.entries[2],
+            Entry {
+                day: 1,
+                part: 2,
+                answer: "42".to_string(),
+                status: Status::Right
+            }
+        );
+        assert_eq!(store.to_string(), STORE);
+        assert!(AnswerStore::try_from("1 1 maybe 3").is_err());
+    }
+
+    #[test]
+    fn test_answers_check() {
+        let store = AnswerStore::try_from(STORE).unwrap();
+
+        assert_eq!(store.check(1, 1, "150"), vec![]);
+        assert_eq!(
+            store.check(1, 1, "100"),
+            vec![Warning::KnownWrong(Status::TooLow)]
+        );
+        assert_eq!(
+            store.check(1, 1, "50"),
+            vec![Warning::NotAbove("100".to_string())]
+        );
+        assert_eq!(
+            store.check(1, 1, "250"),
+            vec![Warning::NotBelow("200".to_string())]
+        );
+        assert_eq!(store.check(1, 2, "42"), vec![]);
+        assert_eq!(
+            store.check(1, 2, "43"),
+            vec![Warning::DiffersFromAccepted("42".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_answers_record() {
+        let mut store = AnswerStore::try_from(STORE).unwrap();
+        assert!(store.record(1, 1, "100", Status::Right).is_ok());
+
+        assert_eq!(store.entries.len(), 3);
+        assert_eq!(store.check(1, 1, "100"), vec![]);
+
+        for answer in ["", " 42", "42\t", "4\n2", "4\r2"] {
+            assert!(store.record(1, 2, answer, Status::TooLow).is_err());
+        }
+        assert!(store.record(1, 2, "ab cd", Status::TooLow).is_ok());
+        assert_eq!(AnswerStore::try_from(store.to_string().as_str()), Ok(store));
+    }
+}
@@ -1,26 +1,271 @@
 use aoc_runner_derive::aoc;
 use aoc_runner_derive::aoc_generator;
 
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::BufRead;
+
 #[aoc_generator(day1)]
 pub fn parse_input(input: &str) -> Vec<u64> {
     input.lines().map(|x| x.parse().unwrap()).collect()
 }
 
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct DepthAnalysis {
+    pub increases: u64,
+    pub decreases: u64,
+    pub plateaus: u64,
+    /// Largest number of consecutive increases
+    pub longest_increasing_run: u64,
+}
+
+impl fmt::Display for DepthAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Increases: {}", self.increases)?;
+        writeln!(f, "Decreases: {}", self.decreases)?;
+        writeln!(f, "Plateaus: {}", self.plateaus)?;
+        writeln!(f, "Longest increasing run: {}", self.longest_increasing_run)
+    }
+}
+
+/// Compares the sums of consecutive sliding windows of measurements, one
+/// measurement at a time. Two consecutive windows share all but one value,
+/// so comparing their sums is the same as comparing the measurement that
+/// enters the window against the one that leaves it. That way only the
+/// last `window_size` measurements need to be kept in memory.
+#[derive(Debug, Clone)]
+pub struct DepthAnalyser {
+    window: VecDeque<u64>,
+    window_size: usize,
+    current_run: u64,
+    analysis: DepthAnalysis,
+}
+
+impl DepthAnalyser {
+    /// Returns `None` for an empty window, which has nothing to compare.
+    pub fn new(window_size: usize) -> Option<Self> {
+        if window_size == 0 {
+            return None;
+        }
+
+        Some(Self {
+            window: VecDeque::with_capacity(window_size + 1),
+            window_size,
+            current_run: 0,
+            analysis: DepthAnalysis::default(),
+        })
+    }
+
+    pub fn push(&mut self, measurement: u64) {
+        self.window.push_back(measurement);
+        if self.window.len() <= self.window_size {
+            return;
+        }
+
+        let leaving = self.window.pop_front().unwrap();
+        if measurement > leaving {
+            self.analysis.increases += 1;
+            self.current_run += 1;
+            self.analysis.longest_increasing_run =
+                std::cmp::max(self.analysis.longest_increasing_run, self.current_run);
+        } else {
+            self.current_run = 0;
+            if measurement < leaving {
+                self.analysis.decreases += 1;
+            } else {
+                self.analysis.plateaus += 1;
+            }
+        }
+    }
+
+    pub fn analysis(&self) -> DepthAnalysis {
+        self.analysis
+    }
+}
+
+pub fn analyse<I>(measurements: I, window_size: usize) -> Option<DepthAnalysis>
+where
+    I: IntoIterator<Item = u64>,
+{
+    let mut analyser = DepthAnalyser::new(window_size)?;
+    for measurement in measurements {
+        analyser.push(measurement);
+    }
+
+    Some(analyser.analysis())
+}
+
+/// Analyses a log of measurements, one per line, as it is read. Only the
+/// current line and the window are in memory, so the log can be of any size.
+pub fn analyse_reader<R: BufRead>(reader: R, window_size: usize) -> Result<DepthAnalysis, String> {
+    let mut analyser = DepthAnalyser::new(window_size).ok_or("Window size must be at least 1")?;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| err.to_string())?;
+        let measurement = line
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid measurement at line {}: {}", i + 1, line))?;
+        analyser.push(measurement);
+    }
+
+    Ok(analyser.analysis())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Jump {
+    /// Index of the measurement after the jump
+    pub index: usize,
+    pub from: u64,
+    pub to: u64,
+}
+
+impl Jump {
+    pub fn size(&self) -> u64 {
+        (self.to as i64 - self.from as i64).unsigned_abs()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SonarReport {
+    pub count: usize,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub window_size: usize,
+    pub moving_averages: Vec<f64>,
+    pub largest_jump: Option<Jump>,
+    pub sigmas: f64,
+    /// Measurements (and their index) further than `sigmas` standard
+    /// deviations from the mean
+    pub outliers: Vec<(usize, u64)>,
+}
+
+impl SonarReport {
+    pub fn new(measurements: &[u64], window_size: usize, sigmas: f64) -> Option<Self> {
+        if measurements.is_empty() || window_size == 0 {
+            return None;
+        }
+
+        let count = measurements.len();
+        let mean = measurements.iter().sum::<u64>() as f64 / count as f64;
+        let variance = measurements
+            .iter()
+            .map(|x| (*x as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        let std_dev = variance.sqrt();
+
+        let moving_averages = measurements
+            .windows(window_size)
+            .map(|window| window.iter().sum::<u64>() as f64 / window_size as f64)
+            .collect();
+
+        let largest_jump = measurements
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| Jump {
+                index: i + 1,
+                from: pair[0],
+                to: pair[1],
+            })
+            .fold(None, |largest: Option<Jump>, jump| match largest {
+                Some(largest) if largest.size() >= jump.size() => Some(largest),
+                _ => Some(jump),
+            });
+
+        let outliers = measurements
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, x)| (*x as f64 - mean).abs() > sigmas * std_dev)
+            .collect();
+
+        Some(Self {
+            count,
+            min: *measurements.iter().min().unwrap(),
+            max: *measurements.iter().max().unwrap(),
+            mean,
+            std_dev,
+            window_size,
+            moving_averages,
+            largest_jump,
+            sigmas,
+            outliers,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let list = |items: Vec<String>| format!("[{}]", items.join(","));
+        let jump = match self.largest_jump {
+            Some(jump) => format!(
+                "{{\"index\":{},\"from\":{},\"to\":{}}}",
+                jump.index, jump.from, jump.to
+            ),
+            None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"count\":{},\"min\":{},\"max\":{},\"mean\":{},\"std_dev\":{},\"window_size\":{},\"moving_averages\":{},\"largest_jump\":{},\"sigmas\":{},\"outliers\":{}}}",
+            self.count,
+            self.min,
+            self.max,
+            self.mean,
+            self.std_dev,
+            self.window_size,
+            list(self.moving_averages.iter().map(|x| x.to_string()).collect()),
+            jump,
+            self.sigmas,
+            list(
+                self.outliers
+                    .iter()
+                    .map(|(i, x)| format!("{{\"index\":{},\"value\":{}}}", i, x))
+                    .collect()
+            ),
+        )
+    }
+}
+
+impl fmt::Display for SonarReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Measurements: {}", self.count)?;
+        writeln!(f, "Min / max: {} / {}", self.min, self.max)?;
+        writeln!(f, "Mean: {:.2} (std. dev. {:.2})", self.mean, self.std_dev)?;
+
+        let averages = self.moving_averages.iter().copied();
+        if let (Some(lowest), Some(highest)) =
+            (averages.clone().reduce(f64::min), averages.reduce(f64::max))
+        {
+            writeln!(
+                f,
+                "Moving average (window {}): {:.2} to {:.2}",
+                self.window_size, lowest, highest
+            )?;
+        }
+        if let Some(jump) = self.largest_jump {
+            writeln!(
+                f,
+                "Largest jump: {} -> {} at #{}",
+                jump.from, jump.to, jump.index
+            )?;
+        }
+
+        write!(f, "Outliers beyond {} sigmas:", self.sigmas)?;
+        for (i, x) in self.outliers.iter() {
+            write!(f, " {} at #{};", x, i)?;
+        }
+        writeln!(f)
+    }
+}
+
 #[aoc(day1, part1)]
 pub fn solve_part1(measurements: &[u64]) -> u64 {
-    measurements.windows(2).fold(0, |total, window| {
-        total + if window[1] > window[0] { 1 } else { 0 }
-    })
+    analyse(measurements.iter().copied(), 1).unwrap().increases
 }
 
 #[aoc(day1, part2)]
 pub fn solve_part2(measurements: &[u64]) -> u64 {
-    let triplets: Vec<&[u64]> = measurements.windows(3).collect();
-    triplets.windows(2).fold(0, |total, window| {
-        let prev_sum: u64 = window[0].iter().sum();
-        let curr_sum: u64 = window[1].iter().sum();
-        total + if curr_sum > prev_sum { 1 } else { 0 }
-    })
+    analyse(measurements.iter().copied(), 3).unwrap().increases
 }
 
 #[cfg(test)]
@@ -38,4 +283,68 @@ mod tests {
         let input: Vec<u64> = vec![199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
         assert_eq!(solve_part2(&input), 5);
     }
+
+    #[test]
+    fn test_day1_analyse() {
+        let input: Vec<u64> = vec![199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+        assert_eq!(
+            analyse(input.iter().copied(), 1),
+            Some(DepthAnalysis {
+                increases: 7,
+                decreases: 2,
+                plateaus: 0,
+                longest_increasing_run: 3,
+            })
+        );
+        assert_eq!(
+            analyse(input.iter().copied(), 3),
+            Some(DepthAnalysis {
+                increases: 5,
+                decreases: 1,
+                plateaus: 1,
+                longest_increasing_run: 4,
+            })
+        );
+        assert_eq!(
+            analyse(input.iter().copied(), 10),
+            Some(DepthAnalysis::default())
+        );
+        assert_eq!(analyse(input.into_iter(), 0), None);
+    }
+
+    #[test]
+    fn test_day1_analyse_reader() {
+        let log = "199\n200\n208\n210\n200\n207\n240\n269\n260\n263\n";
+
+        let analysis = analyse_reader(log.as_bytes(), 3).unwrap();
+        assert_eq!((analysis.increases, analysis.plateaus), (5, 1));
+        assert!(analyse_reader(log.as_bytes(), 0).is_err());
+        assert_eq!(
+            analyse_reader("1\n2\nthree\n".as_bytes(), 1),
+            Err("Invalid measurement at line 3: three".to_string())
+        );
+    }
+
+    #[test]
+    fn test_day1_sonar_report() {
+        let input: Vec<u64> = vec![100, 102, 101, 103, 500, 104, 102];
+        let report = SonarReport::new(&input, 3, 2.0).unwrap();
+
+        assert_eq!(report.count, 7);
+        assert_eq!((report.min, report.max), (100, 500));
+        assert!((report.mean - 1112.0 / 7.0).abs() < 1e-9);
+        assert_eq!(report.moving_averages.len(), 5);
+        assert!((report.moving_averages[0] - 101.0).abs() < 1e-9);
+        assert_eq!(
+            report.largest_jump,
+            Some(Jump {
+                index: 4,
+                from: 103,
+                to: 500
+            })
+        );
+        assert_eq!(report.outliers, vec![(4, 500)]);
+        assert!(SonarReport::new(&[], 3, 2.0).is_none());
+    }
 }
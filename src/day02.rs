@@ -1,92 +1,454 @@
 use aoc_runner_derive::aoc;
 use aoc_runner_derive::aoc_generator;
 
-use std::convert::From;
+use std::convert::TryFrom;
+use std::fmt;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Command {
     Forward(i64),
     Up(i64),
     Down(i64),
+    Back(i64),
+    Turn,
+    Surface,
 }
 
-impl From<&str> for Command {
-    fn from(raw: &str) -> Self {
-        let tokens: Vec<&str> = raw.split(' ').collect();
-        let cmd = tokens[0];
-        let value = tokens[1].parse::<i64>().unwrap();
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
 
-        match cmd {
-            "forward" => Self::Forward(value),
-            "up" => Self::Up(value),
-            "down" => Self::Down(value),
-            _ => panic!("Unrecognized command: {}", raw),
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl TryFrom<&str> for Command {
+    type Error = ParseError;
+
+    fn try_from(raw: &str) -> Result<Self, Self::Error> {
+        match parse_program(raw)?.as_slice() {
+            [Statement::Command(cmd)] => Ok(*cmd),
+            _ => Err(ParseError {
+                line: 1,
+                message: format!("Expected a single command: {}", raw),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Statement {
+    Command(Command),
+    Repeat(usize, Vec<Statement>),
+}
+
+struct Parser {
+    tokens: Vec<(usize, String)>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        let tokens = input
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                // comments go from `#` to the end of the line
+                let code = line.split('#').next().unwrap();
+                code.replace('{', " { ")
+                    .replace('}', " } ")
+                    .split_whitespace()
+                    .map(|token| (i + 1, token.to_string()))
+                    .collect::<Vec<(usize, String)>>()
+            })
+            .collect();
+
+        Self {
+            tokens,
+            position: 0,
+        }
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.position) {
+            Some((line, _)) => *line,
+            None => self.tokens.last().map(|(line, _)| *line).unwrap_or(1),
+        }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line(),
+            message,
+        })
+    }
+
+    fn next_token(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).map(|(_, x)| x.to_owned());
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, verb: &str) -> Result<T, ParseError> {
+        match self.next_token() {
+            Some(token) => match token.parse::<T>() {
+                Ok(value) => Ok(value),
+                Err(_) => {
+                    self.position -= 1;
+                    self.error(format!(
+                        "Expected a number after `{}`, found `{}`",
+                        verb, token
+                    ))
+                }
+            },
+            None => self.error(format!("Expected a number after `{}`", verb)),
+        }
+    }
+
+    fn block(&mut self, inside_repeat: bool) -> Result<Vec<Statement>, ParseError> {
+        let mut statements = vec![];
+
+        loop {
+            let token = match self.next_token() {
+                Some(token) => token,
+                None if inside_repeat => return self.error("Missing `}`".to_string()),
+                None => return Ok(statements),
+            };
+
+            let statement = match token.as_str() {
+                "}" if inside_repeat => return Ok(statements),
+                "forward" => Statement::Command(Command::Forward(self.number(&token)?)),
+                "up" => Statement::Command(Command::Up(self.number(&token)?)),
+                "down" => Statement::Command(Command::Down(self.number(&token)?)),
+                "back" => Statement::Command(Command::Back(self.number(&token)?)),
+                "turn" => Statement::Command(Command::Turn),
+                "surface" => Statement::Command(Command::Surface),
+                "repeat" => {
+                    let times = self.number(&token)?;
+                    match self.next_token() {
+                        Some(token) if token == "{" => {}
+                        Some(_) => {
+                            self.position -= 1;
+                            return self.error("Expected `{` after repeat count".to_string());
+                        }
+                        None => return self.error("Expected `{` after repeat count".to_string()),
+                    }
+                    Statement::Repeat(times, self.block(true)?)
+                }
+                _ => {
+                    self.position -= 1;
+                    return self.error(format!("Unrecognized command: {}", token));
+                }
+            };
+            statements.push(statement);
+        }
+    }
+}
+
+/// Parses a submarine program. Besides the puzzle commands, it supports
+/// `back X`, `turn` (faces the opposite way), `surface`, repeat blocks
+/// like `repeat 3 { forward 2 }` and `#` comments.
+pub fn parse_program(input: &str) -> Result<Vec<Statement>, ParseError> {
+    Parser::new(input).block(false)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct State {
+    pub x: i64,
+    pub y: i64,
+    pub aim: i64,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Trajectory {
+    states: Vec<State>,
+}
+
+impl Trajectory {
+    pub fn push(&mut self, state: State) {
+        self.states.push(state);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &State> {
+        self.states.iter()
+    }
+
+    pub fn max_depth(&self) -> Option<i64> {
+        self.states.iter().map(|state| state.y).max()
+    }
+
+    /// Length of the path followed by the submarine
+    pub fn total_distance(&self) -> f64 {
+        self.states
+            .windows(2)
+            .map(|pair| {
+                let dx = (pair[1].x - pair[0].x) as f64;
+                let dy = (pair[1].y - pair[0].y) as f64;
+                (dx * dx + dy * dy).sqrt()
+            })
+            .sum()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut buffer = "step,x,y,aim\n".to_string();
+        for (i, state) in self.states.iter().enumerate() {
+            buffer.push_str(&format!("{},{},{},{}\n", i, state.x, state.y, state.aim));
+        }
+        buffer
+    }
+
+    /// Renders the trajectory as an SVG polyline. Depth grows downwards,
+    /// same as the `y` axis in SVG.
+    pub fn to_svg(&self) -> String {
+        let min_x = self.states.iter().map(|state| state.x).min().unwrap_or(0);
+        let max_x = self.states.iter().map(|state| state.x).max().unwrap_or(0);
+        let min_y = self.states.iter().map(|state| state.y).min().unwrap_or(0);
+        let max_y = self.states.iter().map(|state| state.y).max().unwrap_or(0);
+        let points: Vec<String> = self
+            .states
+            .iter()
+            .map(|state| format!("{},{}", state.x, state.y))
+            .collect();
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n  <polyline points=\"{}\" fill=\"none\" stroke=\"black\" vector-effect=\"non-scaling-stroke\" />\n</svg>\n",
+            min_x - 1,
+            min_y - 1,
+            max_x - min_x + 2,
+            max_y - min_y + 2,
+            points.join(" ")
+        )
+    }
+}
+
+/// Keeps the states of a submarine once its history is enabled.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    history: Option<Trajectory>,
+}
+
+impl Recorder {
+    fn start(&mut self, state: State) {
+        let mut history = Trajectory::default();
+        history.push(state);
+        self.history = Some(history);
+    }
+
+    fn record(&mut self, state: State) {
+        if let Some(history) = &mut self.history {
+            history.push(state);
         }
     }
 }
 
 pub trait Submarine {
     fn exec(&mut self, cmd: &Command);
+    fn state(&self) -> State;
+    fn recorder(&self) -> &Recorder;
+    fn recorder_mut(&mut self) -> &mut Recorder;
+
+    /// Starts recording the states, from the current one on
+    fn with_history(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        let state = self.state();
+        self.recorder_mut().start(state);
+        self
+    }
+    /// Returns the recorded states, if the submarine keeps its history
+    fn history(&self) -> Option<&Trajectory> {
+        self.recorder().history.as_ref()
+    }
+
+    fn step(&mut self, cmd: &Command) {
+        self.exec(cmd);
+        let state = self.state();
+        self.recorder_mut().record(state);
+    }
     fn run(&mut self, input: &[Command]) {
         for cmd in input {
-            self.exec(cmd);
+            self.step(cmd);
+        }
+    }
+    fn run_program(&mut self, program: &[Statement]) {
+        for statement in program {
+            match statement {
+                Statement::Command(cmd) => self.step(cmd),
+                Statement::Repeat(times, body) => {
+                    for _ in 0..*times {
+                        self.run_program(body);
+                    }
+                }
+            }
         }
     }
 }
 
-#[derive(Debug)]
-pub struct SubmarineV1 {
-    x: i64,
-    y: i64,
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Physics {
+    /// Whether `up` and `down` change the aim (like V2) or the depth (V1)
+    pub use_aim: bool,
+    /// How much the aim goes back towards zero after each move
+    pub aim_drag: i64,
+    pub min_depth: Option<i64>,
+    pub max_depth: Option<i64>,
 }
 
-impl Submarine for SubmarineV1 {
-    fn exec(&mut self, cmd: &Command) {
-        match cmd {
-            Command::Forward(delta) => self.x += delta,
-            Command::Up(delta) => self.y -= delta,
-            Command::Down(delta) => self.y += delta,
-        }
-    }
+impl Physics {
+    pub const V1: Self = Self {
+        use_aim: false,
+        aim_drag: 0,
+        min_depth: None,
+        max_depth: None,
+    };
+
+    pub const V2: Self = Self {
+        use_aim: true,
+        ..Self::V1
+    };
 }
 
+/// A submarine whose behaviour is set by its `Physics`. `Physics::V1` and
+/// `Physics::V2` are the two models from the puzzle, and the others can be
+/// used to try out variations.
 #[derive(Debug)]
-pub struct SubmarineV2 {
+pub struct ConfigurableSubmarine {
+    physics: Physics,
     x: i64,
     y: i64,
     aim: i64,
+    facing: i64,
+    recorder: Recorder,
 }
 
-impl Submarine for SubmarineV2 {
+impl ConfigurableSubmarine {
+    pub fn new(physics: Physics) -> Self {
+        Self {
+            physics,
+            x: 0,
+            y: 0,
+            aim: 0,
+            facing: 1,
+            recorder: Recorder::default(),
+        }
+    }
+
+    fn dive(&mut self, delta: i64) {
+        self.y += delta;
+        if let Some(min_depth) = self.physics.min_depth {
+            self.y = std::cmp::max(self.y, min_depth);
+        }
+        if let Some(max_depth) = self.physics.max_depth {
+            self.y = std::cmp::min(self.y, max_depth);
+        }
+    }
+
+    fn apply_drag(&mut self) {
+        let drag = std::cmp::min(self.physics.aim_drag, self.aim.abs());
+        self.aim -= self.aim.signum() * drag;
+    }
+}
+
+impl Submarine for ConfigurableSubmarine {
     fn exec(&mut self, cmd: &Command) {
         match cmd {
-            Command::Up(delta) => self.aim -= delta,
-            Command::Down(delta) => self.aim += delta,
+            Command::Up(delta) if self.physics.use_aim => self.aim -= delta,
+            Command::Down(delta) if self.physics.use_aim => self.aim += delta,
+            Command::Up(delta) => self.dive(-delta),
+            Command::Down(delta) => self.dive(*delta),
             Command::Forward(delta) => {
-                self.x += delta;
-                self.y += self.aim * delta;
+                self.x += self.facing * delta;
+                self.dive(self.aim * delta);
+                self.apply_drag();
+            }
+            Command::Back(delta) => {
+                self.x -= self.facing * delta;
+                self.dive(-self.aim * delta);
+                self.apply_drag();
             }
+            Command::Turn => self.facing = -self.facing,
+            Command::Surface => {
+                self.aim = 0;
+                self.dive(-self.y);
+            }
+        }
+    }
+
+    fn state(&self) -> State {
+        State {
+            x: self.x,
+            y: self.y,
+            aim: self.aim,
         }
     }
+
+    fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    fn recorder_mut(&mut self) -> &mut Recorder {
+        &mut self.recorder
+    }
+}
+
+/// Submarine models that can be picked by name
+pub const MODELS: &[(&str, Physics)] = &[
+    ("v1", Physics::V1),
+    ("v2", Physics::V2),
+    (
+        "aim-drag",
+        Physics {
+            aim_drag: 1,
+            ..Physics::V2
+        },
+    ),
+    (
+        "max-depth",
+        Physics {
+            max_depth: Some(500),
+            ..Physics::V2
+        },
+    ),
+    (
+        "non-negative",
+        Physics {
+            min_depth: Some(0),
+            ..Physics::V2
+        },
+    ),
+];
+
+pub fn model(name: &str) -> Option<ConfigurableSubmarine> {
+    MODELS
+        .iter()
+        .find(|(model_name, _)| *model_name == name)
+        .map(|(_, physics)| ConfigurableSubmarine::new(*physics))
 }
 
 #[aoc_generator(day2)]
-pub fn parse_input(input: &str) -> Vec<Command> {
-    input.lines().map(Command::from).collect()
+pub fn parse_input(input: &str) -> Vec<Statement> {
+    parse_program(input).unwrap_or_else(|err| panic!("{}", err))
 }
 
 #[aoc(day2, part1)]
-pub fn solve_part1(input: &[Command]) -> i64 {
-    let mut submarine = SubmarineV1 { x: 0, y: 0 };
-    submarine.run(input);
+pub fn solve_part1(input: &[Statement]) -> i64 {
+    let mut submarine = ConfigurableSubmarine::new(Physics::V1);
+    submarine.run_program(input);
 
     submarine.x * submarine.y
 }
 
 #[aoc(day2, part2)]
-pub fn solve_part2(input: &[Command]) -> i64 {
-    let mut submarine = SubmarineV2 { x: 0, y: 0, aim: 0 };
-    submarine.run(input);
+pub fn solve_part2(input: &[Statement]) -> i64 {
+    let mut submarine = ConfigurableSubmarine::new(Physics::V2);
+    submarine.run_program(input);
 
     submarine.x * submarine.y
 }
@@ -95,10 +457,21 @@ pub fn solve_part2(input: &[Command]) -> i64 {
 mod tests {
     use super::*;
 
+    /// Unrolls repeat blocks into a flat list of commands.
+    fn expand(program: &[Statement]) -> Vec<Command> {
+        program
+            .iter()
+            .flat_map(|statement| match statement {
+                Statement::Command(cmd) => vec![*cmd],
+                Statement::Repeat(times, body) => expand(body).repeat(*times),
+            })
+            .collect()
+    }
+
     #[test]
     pub fn test_day2_parse_input() {
         let input = String::from("forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2");
-        let parsed = parse_input(&input);
+        let parsed = expand(&parse_input(&input));
         assert_eq!(parsed.len(), 6);
         assert_eq!(
             parsed,
@@ -123,8 +496,9 @@ mod tests {
             Command::Down(8),
             Command::Forward(2),
         ];
+        let program: Vec<Statement> = commands.into_iter().map(Statement::Command).collect();
 
-        assert_eq!(solve_part1(&commands), 150);
+        assert_eq!(solve_part1(&program), 150);
     }
 
     #[test]
@@ -137,7 +511,136 @@ mod tests {
             Command::Down(8),
             Command::Forward(2),
         ];
+        let program: Vec<Statement> = commands.into_iter().map(Statement::Command).collect();
+
+        assert_eq!(solve_part2(&program), 900);
+    }
+
+    #[test]
+    pub fn test_day2_parse_program() {
+        let input = "# a comment\nrepeat 2 {\n  forward 1 # move\n  repeat 2 { down 3 }\n}\nturn\nback 4\nsurface";
+        let program = parse_program(input).unwrap();
+
+        assert_eq!(
+            program,
+            vec![
+                Statement::Repeat(
+                    2,
+                    vec![
+                        Statement::Command(Command::Forward(1)),
+                        Statement::Repeat(2, vec![Statement::Command(Command::Down(3))]),
+                    ]
+                ),
+                Statement::Command(Command::Turn),
+                Statement::Command(Command::Back(4)),
+                Statement::Command(Command::Surface),
+            ]
+        );
+        assert_eq!(expand(&program).len(), 9);
+
+        // repeat blocks are kept as they are until the program runs
+        let program = parse_input("repeat 1000000000 { forward 1 }");
+        assert_eq!(program.len(), 1);
+        assert_eq!(solve_part1(&parse_input("repeat 1000 { forward 1 }")), 0);
+        assert_eq!(
+            solve_part2(&parse_input("down 1\nrepeat 1000 { forward 1 }")),
+            1000 * 1000
+        );
+    }
+
+    #[test]
+    pub fn test_day2_parse_errors() {
+        let error = |input: &str| parse_program(input).unwrap_err().to_string();
+
+        assert_eq!(
+            error("forward 1\njump 2"),
+            "line 2: Unrecognized command: jump"
+        );
+        assert_eq!(
+            error("up x"),
+            "line 1: Expected a number after `up`, found `x`"
+        );
+        assert_eq!(error("repeat 2 {\nforward 1"), "line 2: Missing `}`");
+        assert_eq!(
+            error("repeat 2 forward 1"),
+            "line 1: Expected `{` after repeat count"
+        );
+        assert_eq!(error("forward 1 }"), "line 1: Unrecognized command: }");
+        assert_eq!(
+            error("forward 1\nrepeat 2"),
+            "line 2: Expected `{` after repeat count"
+        );
+        assert!(Command::try_from("forward 1 up 2").is_err());
+        assert_eq!(Command::try_from("back 3"), Ok(Command::Back(3)));
+    }
+
+    #[test]
+    pub fn test_day2_run_program() {
+        let program = parse_program("repeat 3 { forward 2 down 1 }\nturn\nback 1").unwrap();
+
+        let mut v1 = ConfigurableSubmarine::new(Physics::V1);
+        v1.run_program(&program);
+        assert_eq!((v1.x, v1.y), (7, 3));
+
+        let mut v2 = ConfigurableSubmarine::new(Physics::V2);
+        v2.run_program(&program);
+        assert_eq!((v2.x, v2.y, v2.aim), (7, 3, 3));
+
+        v2.exec(&Command::Surface);
+        assert_eq!((v2.y, v2.aim), (0, 0));
+    }
+
+    #[test]
+    pub fn test_day2_history() {
+        let commands = expand(&parse_input("forward 3\ndown 4\nforward 1\nup 8"));
+        let mut submarine = ConfigurableSubmarine::new(Physics::V1).with_history();
+        submarine.run(&commands);
+        let history = submarine.history().unwrap();
+
+        assert_eq!(history.iter().count(), 5);
+        assert_eq!(history.max_depth(), Some(4));
+        assert!((history.total_distance() - 16.0).abs() < 1e-9);
+        assert_eq!(
+            history.to_csv(),
+            "step,x,y,aim\n0,0,0,0\n1,3,0,0\n2,3,4,0\n3,4,4,0\n4,4,-4,0\n"
+        );
+        assert!(history.to_svg().contains("points=\"0,0 3,0 3,4 4,4 4,-4\""));
+
+        let mut submarine = ConfigurableSubmarine::new(Physics::V2);
+        submarine.run(&commands);
+        assert!(submarine.history().is_none());
+    }
+
+    #[test]
+    pub fn test_day2_models() {
+        let commands = expand(&parse_input(
+            "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2",
+        ));
+        let final_state = |name: &str| {
+            let mut submarine = model(name).unwrap();
+            submarine.run(&commands);
+            let state = submarine.state();
+            (state.x, state.y, state.aim)
+        };
+
+        assert_eq!(final_state("v1"), (15, 10, 0));
+        assert_eq!(final_state("v2"), (15, 60, 10));
+        assert_eq!(final_state("aim-drag"), (15, 58, 8));
+        assert!(model("warp-drive").is_none());
+
+        let mut submarine = model("non-negative").unwrap();
+        submarine.run_program(&parse_input("up 2\nforward 3"));
+        assert_eq!(
+            submarine.state(),
+            State {
+                x: 3,
+                y: 0,
+                aim: -2
+            }
+        );
 
-        assert_eq!(solve_part2(&commands), 900);
+        let mut submarine = model("max-depth").unwrap();
+        submarine.run_program(&parse_input("down 100\nforward 6"));
+        assert_eq!(submarine.state().y, 500);
     }
 }
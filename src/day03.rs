@@ -1,71 +1,284 @@
 use aoc_runner_derive::aoc;
 use aoc_runner_derive::aoc_generator;
 
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::day06::BigUint;
+
 #[aoc_generator(day3)]
 pub fn parse_input(input: &str) -> Vec<String> {
     input.lines().map(|x| x.to_string()).collect()
 }
 
-fn build_report(input: &[String]) -> (u32, Vec<u32>) {
-    let n_bits = input.get(0).unwrap().len() as u32;
-    let report: Vec<u32> = input.iter().map(|x| u32::from_str_radix(x, 2).unwrap()).collect();
-    (n_bits, report)
+/// A fixed-width sequence of bits, packed into 64-bit words. Bits are
+/// indexed from the left, in the same order they appear in the report.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Bits {
+    words: Vec<u64>,
+    len: usize,
 }
 
-#[aoc(day3, part1)]
-pub fn solve_part1(input: &[String]) -> u32 {
-    let (n_bits, report) = build_report(input);
-    let mut gamma_rate = 0;
-
-    for i in 0..n_bits {
-        let mask = 2_u32.pow(i);
-        let ones = report.iter().filter(|x| *x & mask == mask).count();
-        if ones > report.len() / 2 {
-            gamma_rate += mask
+impl Bits {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    pub fn inverted(&self) -> Self {
+        let mut res = Self::new(self.len);
+        for i in 0..self.len {
+            res.set(i, !self.get(i));
+        }
+        res
+    }
+
+    /// Returns the numeric value, or `None` if it doesn't fit in 128 bits.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.len > 128 {
+            return None;
+        }
+        Some((0..self.len).fold(0, |value, i| value << 1 | self.get(i) as u128))
+    }
+
+    /// Returns the numeric value, whatever the width.
+    pub fn to_biguint(&self) -> BigUint {
+        // 32 bits at a time, most significant first
+        (0..self.len)
+            .step_by(32)
+            .fold(BigUint::from_u64(0), |value, start| {
+                let end = std::cmp::min(start + 32, self.len);
+                let chunk = (start..end).fold(0, |chunk, i| chunk << 1 | self.get(i) as u64);
+                value
+                    .mul(&BigUint::from_u64(1 << (end - start)))
+                    .add(&BigUint::from_u64(chunk))
+            })
+    }
+}
+
+impl TryFrom<&str> for Bits {
+    type Error = String;
+
+    fn try_from(raw: &str) -> Result<Self, Self::Error> {
+        let mut bits = Self::new(raw.len());
+        for (i, c) in raw.chars().enumerate() {
+            match c {
+                '0' => {}
+                '1' => bits.set(i, true),
+                _ => return Err(format!("Invalid bit `{}` in {}", c, raw)),
+            }
         }
+        Ok(bits)
     }
+}
 
-    let mask = 2_u32.pow(n_bits) - 1; // so for 5 bits we would get this mask 0b11111
-    let epsilon_rate = !gamma_rate & mask ;
+impl fmt::Display for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let buffer: String = (0..self.len)
+            .map(|i| if self.get(i) { '1' } else { '0' })
+            .collect();
+        write!(f, "{}", buffer)
+    }
+}
 
-    gamma_rate * epsilon_rate
+#[derive(Debug, PartialEq, Clone)]
+pub struct Report {
+    width: usize,
+    rows: Vec<Bits>,
+}
+
+pub fn build_report(input: &[String]) -> Result<Report, String> {
+    let width = input.first().ok_or("Empty report")?.len();
+    let rows = input
+        .iter()
+        .enumerate()
+        .map(|(i, raw)| {
+            if raw.len() != width {
+                return Err(format!(
+                    "Line {} has {} bits, expected {}",
+                    i + 1,
+                    raw.len(),
+                    width
+                ));
+            }
+            Bits::try_from(raw.as_str())
+        })
+        .collect::<Result<Vec<Bits>, String>>()?;
+
+    Ok(Report { width, rows })
+}
+
+impl Report {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    fn stats_at(&self, rows: &[usize], column: usize) -> ColumnStats {
+        let ones = rows.iter().filter(|&&i| self.rows[i].get(column)).count();
+        ColumnStats {
+            ones,
+            zeros: rows.len() - ones,
+        }
+    }
+
+    pub fn column_stats(&self) -> Vec<ColumnStats> {
+        let all: Vec<usize> = (0..self.rows.len()).collect();
+        (0..self.width)
+            .map(|column| self.stats_at(&all, column))
+            .collect()
+    }
+
+    /// Columns with as many ones as zeros
+    pub fn ties(&self) -> Vec<usize> {
+        self.column_stats()
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.is_tie())
+            .map(|(column, _)| column)
+            .collect()
+    }
+
+    fn rate(&self, criteria: Criteria, tie: TieBreak) -> Bits {
+        let mut rate = Bits::new(self.width);
+        for (column, stats) in self.column_stats().iter().enumerate() {
+            rate.set(column, stats.pick(criteria, tie));
+        }
+        rate
+    }
+
+    pub fn gamma_rate_with(&self, tie: TieBreak) -> Bits {
+        self.rate(Criteria::MostCommon, tie)
+    }
+
+    pub fn epsilon_rate_with(&self, tie: TieBreak) -> Bits {
+        self.rate(Criteria::LeastCommon, tie)
+    }
+
+    /// Ties count as zero, like in the original solution
+    pub fn gamma_rate(&self) -> Bits {
+        self.gamma_rate_with(TieBreak::Zero)
+    }
+
+    pub fn epsilon_rate(&self) -> Bits {
+        self.gamma_rate().inverted()
+    }
+
+    /// Narrows down the rows column by column until only one is left. The
+    /// candidates are kept as a list of row indices that shrinks in place.
+    pub fn rating(&self, criteria: Criteria, tie: TieBreak) -> Rating {
+        let mut candidates: Vec<usize> = (0..self.rows.len()).collect();
+        let mut tied_columns = vec![];
+
+        for column in 0..self.width {
+            let stats = self.stats_at(&candidates, column);
+            if stats.ones == 0 || stats.zeros == 0 {
+                // every candidate has the same bit, so there is nothing to
+                // filter (and the least common bit would leave none)
+                continue;
+            }
+            if stats.is_tie() {
+                tied_columns.push(column);
+            }
+            let keep = stats.pick(criteria, tie);
+            candidates.retain(|&i| self.rows[i].get(column) == keep);
+            if candidates.len() == 1 {
+                break;
+            }
+        }
+
+        Rating {
+            value: self.rows[candidates[0]].clone(),
+            tied_columns,
+        }
+    }
+
+    pub fn oxygen_rating(&self) -> Bits {
+        self.rating(Criteria::MostCommon, TieBreak::One).value
+    }
+
+    pub fn co2_rating(&self) -> Bits {
+        self.rating(Criteria::LeastCommon, TieBreak::Zero).value
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum BitCriteria {
+pub enum Criteria {
     MostCommon,
     LeastCommon,
 }
 
-fn filter_report(bit_index: u32, list: &[u32], criteria: BitCriteria) -> Vec<u32> {
-    let mask = 2_u32.pow(bit_index);
-    let ones: Vec<u32> = list.to_vec().into_iter().filter(|x| x & mask == mask).collect();
-    let zeros: Vec<u32> = list.to_vec().into_iter().filter(|x| x & mask != mask).collect();
+/// Which bit to pick when a column has as many ones as zeros
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TieBreak {
+    One,
+    Zero,
+}
 
-    match criteria {
-        BitCriteria::MostCommon => if ones.len() >= zeros.len() { ones } else { zeros },
-        BitCriteria::LeastCommon => if zeros.len() <= ones.len() { zeros } else { ones },
-    }
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ColumnStats {
+    pub ones: usize,
+    pub zeros: usize,
 }
 
-fn get_rating(n_bits: u32, list: &[u32], criteria: BitCriteria) -> u32 {
-    let mut candidates = list.to_vec();
-    for i in (0..n_bits).rev() {
-        candidates = filter_report(i, &candidates, criteria);
-        if candidates.len() == 1 { break; }
+impl ColumnStats {
+    pub fn is_tie(&self) -> bool {
+        self.ones == self.zeros
     }
 
-    *candidates.get(0).unwrap()
+    pub fn pick(&self, criteria: Criteria, tie: TieBreak) -> bool {
+        if self.is_tie() {
+            return tie == TieBreak::One;
+        }
+        match criteria {
+            Criteria::MostCommon => self.ones > self.zeros,
+            Criteria::LeastCommon => self.ones < self.zeros,
+        }
+    }
 }
 
-#[aoc(day3, part2)]
-pub fn solve_part2(input: &[String]) -> u32 {
-    let (n_bits, report) = build_report(input);
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rating {
+    pub value: Bits,
+    /// Columns where the remaining candidates were tied
+    pub tied_columns: Vec<usize>,
+}
 
-    let oxygen_rating = get_rating(n_bits, &report, BitCriteria::MostCommon);
-    let co2_rating = get_rating(n_bits, &report, BitCriteria::LeastCommon);
+fn product(a: &Bits, b: &Bits) -> BigUint {
+    a.to_biguint().mul(&b.to_biguint())
+}
 
-    oxygen_rating * co2_rating
+#[aoc(day3, part1)]
+pub fn solve_part1(input: &[String]) -> Result<BigUint, String> {
+    let report = build_report(input)?;
+    Ok(product(&report.gamma_rate(), &report.epsilon_rate()))
+}
+
+#[aoc(day3, part2)]
+pub fn solve_part2(input: &[String]) -> Result<BigUint, String> {
+    let report = build_report(input)?;
+    Ok(product(&report.oxygen_rating(), &report.co2_rating()))
 }
 
 #[cfg(test)]
@@ -74,7 +287,7 @@ mod tests {
 
     #[test]
     fn test_day3_part1() {
-        let input: Vec<String> = vec![
+        let input: Vec<String> = [
             "00100",
             "11110",
             "10110",
@@ -88,12 +301,12 @@ mod tests {
             "00010",
             "01010",
         ].iter().map(|x| x.to_string()).collect();
-        assert_eq!(solve_part1(&input), 198)
+        assert_eq!(solve_part1(&input), Ok(BigUint::from_u64(198)))
     }
 
     #[test]
     fn test_day3_part2() {
-        let input: Vec<String> = vec![
+        let input: Vec<String> = [
             "00100",
             "11110",
             "10110",
@@ -107,6 +320,106 @@ mod tests {
             "00010",
             "01010",
         ].iter().map(|x| x.to_string()).collect();
-        assert_eq!(solve_part2(&input), 230);
+        assert_eq!(solve_part2(&input), Ok(BigUint::from_u64(230)));
+    }
+
+    #[test]
+    fn test_day3_bits() {
+        let bits = Bits::try_from("10110").unwrap();
+        assert_eq!(bits.to_u128(), Some(22));
+        assert_eq!(bits.inverted().to_string(), "01001");
+
+        let wide = "1".repeat(100) + &"0".repeat(100);
+        let bits = Bits::try_from(wide.as_str()).unwrap();
+        assert_eq!(bits.len(), 200);
+        assert!(bits.get(99));
+        assert!(!bits.get(100));
+        assert_eq!(bits.to_u128(), None);
+        assert_eq!(bits.to_string(), wide);
+        // (2^100 - 1) * 2^100
+        assert_eq!(
+            bits.to_biguint().to_string(),
+            "1606938044258990275541962092339894951921974764381296132096000"
+        );
+        assert_eq!(
+            Bits::try_from("10110").unwrap().to_biguint().to_u64(),
+            Some(22)
+        );
+    }
+
+    #[test]
+    fn test_day3_build_report() {
+        let input = |rows: &[&str]| rows.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(build_report(&input(&["101", "011"])).unwrap().width(), 3);
+        assert_eq!(build_report(&[]), Err("Empty report".to_string()));
+        assert_eq!(
+            build_report(&input(&["101", "01"])),
+            Err("Line 2 has 2 bits, expected 3".to_string())
+        );
+        assert!(build_report(&input(&["102"])).is_err());
+    }
+
+    #[test]
+    fn test_day3_wide_report() {
+        let rows: Vec<String> = ["1", "1", "0"].iter().map(|x| x.repeat(40) + "1").collect();
+        let report = build_report(&rows).unwrap();
+
+        assert_eq!(report.gamma_rate().to_string(), "1".repeat(41));
+        assert_eq!(
+            report.oxygen_rating(),
+            Bits::try_from(rows[0].as_str()).unwrap()
+        );
+        assert_eq!(
+            report.co2_rating(),
+            Bits::try_from(rows[2].as_str()).unwrap()
+        );
+
+        // 100 bits each, so the product doesn't even fit in 128 bits
+        let half = |a: &str, b: &str| a.repeat(50) + &b.repeat(50);
+        let rows = vec![half("1", "0"), half("1", "0"), half("0", "1")];
+        assert_eq!(
+            solve_part1(&rows).unwrap().to_string(),
+            "1427247692705957345757085512991818042883178496"
+        );
+        assert_eq!(solve_part1(&[]), Err("Empty report".to_string()));
+    }
+
+    #[test]
+    fn test_day3_column_stats() {
+        let rows: Vec<String> = ["110", "011", "100", "001"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let report = build_report(&rows).unwrap();
+
+        assert_eq!(
+            report.column_stats(),
+            vec![
+                ColumnStats { ones: 2, zeros: 2 },
+                ColumnStats { ones: 2, zeros: 2 },
+                ColumnStats { ones: 2, zeros: 2 },
+            ]
+        );
+        assert_eq!(report.ties(), vec![0, 1, 2]);
+        assert_eq!(report.gamma_rate().to_string(), "000");
+        assert_eq!(report.gamma_rate_with(TieBreak::One).to_string(), "111");
+        assert_eq!(report.epsilon_rate_with(TieBreak::Zero).to_string(), "000");
+
+        let oxygen = report.rating(Criteria::MostCommon, TieBreak::One);
+        assert_eq!(oxygen.value.to_string(), "110");
+        assert_eq!(oxygen.tied_columns, vec![0, 1]);
+        let co2 = report.rating(Criteria::LeastCommon, TieBreak::Zero);
+        assert_eq!(co2.value.to_string(), "001");
+    }
+
+    #[test]
+    fn test_day3_rating_shared_bits() {
+        let rows: Vec<String> = ["00", "01"].iter().map(|x| x.to_string()).collect();
+        let report = build_report(&rows).unwrap();
+
+        assert_eq!(report.oxygen_rating().to_string(), "01");
+        assert_eq!(report.co2_rating().to_string(), "00");
+        assert_eq!(solve_part2(&rows), Ok(BigUint::from_u64(0)));
     }
-}
\ No newline at end of file
+}
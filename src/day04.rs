@@ -1,8 +1,7 @@
 use aoc_runner_derive::aoc;
 
-use std::convert::From;
-
-const CARD_SIZE: usize = 5;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Number {
@@ -19,27 +18,73 @@ impl Number {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// What counts as a bingo. By default, only full rows and columns do.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Rules {
+    pub diagonals: bool,
+    /// Only a card with every number marked wins
+    pub full_card: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Card {
-    numbers: [Number; CARD_SIZE * CARD_SIZE],
+    size: usize,
+    numbers: Vec<Number>,
+    /// Positions of each number in the card
+    index: HashMap<u64, Vec<usize>>,
 }
 
-impl From<&str> for Card {
-    fn from(raw: &str) -> Self {
-        let mut card = Card {
-            numbers: [Number::Unmarked(0); CARD_SIZE * CARD_SIZE],
-        };
-        let raw_numbers = raw.split_whitespace().map(|x| x.parse::<u64>().unwrap());
+impl TryFrom<&str> for Card {
+    type Error = String;
+
+    /// Parses an NxN card. The size is given by the number of rows, and
+    /// every row must have exactly that many numbers.
+    fn try_from(raw: &str) -> Result<Self, Self::Error> {
+        let rows: Vec<&str> = raw.lines().filter(|x| !x.trim().is_empty()).collect();
+        let size = rows.len();
+        let mut numbers = Vec::with_capacity(size * size);
 
-        for (i, x) in raw_numbers.into_iter().enumerate() {
-            card.numbers[i] = Number::Unmarked(x);
+        for (i, row) in rows.iter().enumerate() {
+            let values = row
+                .split_whitespace()
+                .map(|x| x.parse::<u64>().map(Number::Unmarked))
+                .collect::<Result<Vec<Number>, _>>()
+                .map_err(|err| format!("Invalid number in row {}: {}", i + 1, err))?;
+            if values.len() != size {
+                return Err(format!(
+                    "Row {} has {} numbers, expected {}",
+                    i + 1,
+                    values.len(),
+                    size
+                ));
+            }
+            numbers.extend(values);
+        }
+
+        if size == 0 {
+            return Err("Empty card".to_string());
+        }
+
+        let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, number) in numbers.iter().enumerate() {
+            if let Number::Unmarked(value) = number {
+                index.entry(*value).or_default().push(i);
+            }
         }
 
-        card
+        Ok(Self {
+            size,
+            numbers,
+            index,
+        })
     }
 }
 
 impl Card {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
     fn is_bingo_at_range(&self, range: std::ops::Range<usize>, step: usize) -> bool {
         for i in range.step_by(step) {
             if !self.numbers[i].is_marked() {
@@ -49,14 +94,29 @@ impl Card {
         true
     }
 
-    fn is_bingo(&self, i: usize) -> bool {
-        let col = i % CARD_SIZE;
-        let row = i / CARD_SIZE;
+    fn is_bingo(&self, i: usize, rules: &Rules) -> bool {
+        let n = self.size;
+        if rules.full_card {
+            return self.numbers.iter().all(|x| x.is_marked());
+        }
+
+        let col = i % n;
+        let row = i / n;
+
+        let row_range = (n * row)..(n * (row + 1));
+        let col_range = col..(n * n);
+        if self.is_bingo_at_range(row_range, 1) || self.is_bingo_at_range(col_range, n) {
+            return true;
+        }
 
-        let row_range = (CARD_SIZE * row)..(CARD_SIZE * (row + 1));
-        let col_range = col..(CARD_SIZE * CARD_SIZE);
+        if rules.diagonals && n > 1 {
+            let main_diagonal = row == col && self.is_bingo_at_range(0..(n * n), n + 1);
+            let anti_diagonal =
+                row + col == n - 1 && self.is_bingo_at_range((n - 1)..(n * n - 1), n - 1);
+            return main_diagonal || anti_diagonal;
+        }
 
-        self.is_bingo_at_range(row_range, 1) || self.is_bingo_at_range(col_range, CARD_SIZE)
+        false
     }
 
     fn score(&self) -> u64 {
@@ -70,71 +130,189 @@ impl Card {
     }
 
     pub fn mark(&mut self, value: u64) -> Option<u64> {
-        let position = self
-            .numbers
-            .iter()
-            .position(|x| *x == Number::Unmarked(value));
-        if let Some(i) = position {
-            self.numbers[i] = Number::Marked(value);
-            if self.is_bingo(i) {
-                return Some(self.score() * value);
+        self.mark_with(value, &Rules::default())
+    }
+
+    pub fn mark_with(&mut self, value: u64, rules: &Rules) -> Option<u64> {
+        let positions = match self.index.get(&value) {
+            Some(positions) => positions.clone(),
+            None => return None,
+        };
+
+        let mut is_bingo = false;
+        for i in positions {
+            if self.numbers[i].is_marked() {
+                continue;
             }
+            self.numbers[i] = Number::Marked(value);
+            is_bingo = is_bingo || self.is_bingo(i, rules);
+        }
+
+        if is_bingo {
+            Some(self.score() * value)
+        } else {
+            None
+        }
+    }
+
+    /// Positions that, once all of them are marked, make a bingo.
+    fn winning_lines(&self, rules: &Rules) -> Vec<Vec<usize>> {
+        let n = self.size;
+        if rules.full_card {
+            return vec![(0..n * n).collect()];
+        }
+
+        let mut lines = vec![];
+        for i in 0..n {
+            lines.push((n * i..n * (i + 1)).collect());
+            lines.push((i..n * n).step_by(n).collect());
+        }
+        if rules.diagonals {
+            lines.push((0..n).map(|i| i * (n + 1)).collect());
+            lines.push((0..n).map(|i| (i + 1) * (n - 1)).collect());
         }
 
-        None
+        lines
+    }
+
+    /// Returns the index of the draw that would make this card win, without
+    /// playing the game. A line wins on the latest draw of its numbers, so
+    /// the card wins on the earliest of those.
+    pub fn winning_draw(&self, draws: &[u64], rules: &Rules) -> Option<usize> {
+        let mut drawn_at: HashMap<u64, usize> = HashMap::new();
+        for (i, x) in draws.iter().enumerate() {
+            drawn_at.entry(*x).or_insert(i);
+        }
+
+        self.winning_lines(rules)
+            .iter()
+            .filter_map(|line| {
+                line.iter()
+                    .map(|&i| match self.numbers[i] {
+                        Number::Marked(_) => Some(0),
+                        Number::Unmarked(value) => drawn_at.get(&value).copied(),
+                    })
+                    .collect::<Option<Vec<usize>>>()
+                    .and_then(|x| x.into_iter().max())
+            })
+            .min()
     }
 }
 
-fn parse_input(input: &str) -> (Vec<u64>, Vec<Card>) {
+pub fn parse_game(input: &str) -> Result<(Vec<u64>, Vec<Card>), String> {
     let mut chunks = input.split("\n\n");
     // the first chunk contains the numbers to be drawn
     let draws: Vec<u64> = chunks
         .next()
         .unwrap()
+        .trim()
         .split(',')
-        .map(|x| x.parse::<u64>().unwrap())
-        .collect();
+        .map(|x| x.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>()
+        .map_err(|err| format!("Invalid draw: {}", err))?;
     // following chunks contain the bingo cards
-    let cards: Vec<Card> = chunks.map(Card::from).collect();
+    let cards = chunks
+        .filter(|x| !x.trim().is_empty())
+        .enumerate()
+        .map(|(i, raw)| Card::try_from(raw).map_err(|err| format!("Card {}: {}", i + 1, err)))
+        .collect::<Result<Vec<Card>, String>>()?;
 
-    (draws, cards)
+    Ok((draws, cards))
 }
 
-#[aoc(day4, part1)]
-pub fn solve_part1(input: &str) -> u64 {
-    let (draws, mut cards) = parse_input(input);
-
-    for draw in draws.into_iter() {
-        for card in cards.iter_mut() {
-            if let Some(score) = card.mark(draw) {
-                return score;
-            };
-        }
-    }
-
-    0
+fn parse_input(input: &str) -> (Vec<u64>, Vec<Card>) {
+    parse_game(input).unwrap_or_else(|err| panic!("{}", err))
 }
 
-#[aoc(day4, part2)]
-pub fn solve_part2(input: &str) -> u64 {
-    let (draws, mut cards) = parse_input(input);
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Win {
+    pub card: usize,
+    /// Index of the draw that completed the card
+    pub draw: usize,
+    pub number: u64,
+    pub score: u64,
+}
 
-    let mut latest_winner_score: u64 = 0;
-    let mut to_ignore = Vec::<usize>::new();
+/// Plays the whole game and returns every winner, in the order they won.
+/// Cards winning on the same draw are ordered by their index. Cards that
+/// never win are left out.
+pub fn replay(draws: &[u64], cards: &[Card], rules: &Rules) -> Vec<Win> {
+    let mut cards = cards.to_vec();
+    let mut has_won = vec![false; cards.len()];
+    let mut winners = vec![];
 
-    for draw in draws.into_iter() {
+    for (draw, number) in draws.iter().copied().enumerate() {
         for (i, card) in cards.iter_mut().enumerate() {
-            if to_ignore.contains(&i) {
+            if has_won[i] {
                 continue;
             }
-            if let Some(score) = card.mark(draw) {
-                latest_winner_score = score;
-                to_ignore.push(i)
-            };
+            if let Some(score) = card.mark_with(number, rules) {
+                has_won[i] = true;
+                winners.push(Win {
+                    card: i,
+                    draw,
+                    number,
+                    score,
+                });
+            }
         }
     }
 
-    latest_winner_score
+    winners
+}
+
+/// Number of draws until the card wins, if it ever does.
+pub fn draws_to_win(card: &Card, draws: &[u64], rules: &Rules) -> Option<usize> {
+    card.winning_draw(draws, rules).map(|x| x + 1)
+}
+
+/// Returns the index of the first card to win with the draws in the given
+/// order. Ties are broken like in the game: the lowest card index wins.
+pub fn first_winner(draws: &[u64], cards: &[Card], rules: &Rules) -> Option<usize> {
+    cards
+        .iter()
+        .enumerate()
+        .filter_map(|(i, card)| card.winning_draw(draws, rules).map(|draw| (draw, i)))
+        .min()
+        .map(|(_, i)| i)
+}
+
+/// Returns the first card to win if the draw at index `removed` is skipped.
+pub fn winner_without(
+    draws: &[u64],
+    cards: &[Card],
+    rules: &Rules,
+    removed: usize,
+) -> Option<usize> {
+    let draws: Vec<u64> = draws
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != removed)
+        .map(|(_, x)| *x)
+        .collect();
+    first_winner(&draws, cards, rules)
+}
+
+/// Returns the indices of the draws that, if skipped, would make the card
+/// at index `card` be the first one to win.
+pub fn removals_to_win(draws: &[u64], cards: &[Card], rules: &Rules, card: usize) -> Vec<usize> {
+    (0..draws.len())
+        .filter(|i| winner_without(draws, cards, rules, *i) == Some(card))
+        .collect()
+}
+
+#[aoc(day4, part1)]
+pub fn solve_part1(input: &str) -> u64 {
+    let (draws, cards) = parse_input(input);
+    let winners = replay(&draws, &cards, &Rules::default());
+    winners.first().map_or(0, |x| x.score)
+}
+
+#[aoc(day4, part2)]
+pub fn solve_part2(input: &str) -> u64 {
+    let (draws, cards) = parse_input(input);
+    let winners = replay(&draws, &cards, &Rules::default());
+    winners.last().map_or(0, |x| x.score)
 }
 
 #[cfg(test)]
@@ -171,4 +349,104 @@ mod tests {
     fn test_day4_part2() {
         assert_eq!(solve_part2(INPUT), 1924);
     }
+
+    #[test]
+    fn test_day4_card_validation() {
+        assert_eq!(Card::try_from("1 2\n3 4").unwrap().size(), 2);
+        assert_eq!(
+            Card::try_from("1 2\n3 4 5"),
+            Err("Row 2 has 3 numbers, expected 2".to_string())
+        );
+        assert_eq!(
+            Card::try_from("1 2 3\n4 5 6"),
+            Err("Row 1 has 3 numbers, expected 2".to_string())
+        );
+        assert!(Card::try_from("1 x\n3 4").is_err());
+        assert!(parse_game("1,2\n\n1 2\n3").is_err());
+    }
+
+    #[test]
+    fn test_day4_rules() {
+        let card = Card::try_from("1 2 3\n4 5 6\n7 8 9").unwrap();
+
+        let diagonals = Rules {
+            diagonals: true,
+            full_card: false,
+        };
+        let mut plain = card.clone();
+        assert_eq!(plain.mark(3), None);
+        assert_eq!(plain.mark(5), None);
+        assert_eq!(plain.mark(7), None);
+        let mut with_diagonals = card.clone();
+        assert_eq!(with_diagonals.mark_with(3, &diagonals), None);
+        assert_eq!(with_diagonals.mark_with(5, &diagonals), None);
+        assert_eq!(
+            with_diagonals.mark_with(7, &diagonals),
+            Some((1 + 2 + 4 + 6 + 8 + 9) * 7)
+        );
+
+        let full_card = Rules {
+            diagonals: false,
+            full_card: true,
+        };
+        let mut full = card;
+        for x in 1..9 {
+            assert_eq!(full.mark_with(x, &full_card), None);
+        }
+        assert_eq!(full.mark_with(9, &full_card), Some(0));
+    }
+
+    #[test]
+    fn test_day4_replay() {
+        let (draws, cards) = parse_input(INPUT);
+        let winners = replay(&draws, &cards, &Rules::default());
+
+        assert_eq!(
+            winners,
+            vec![
+                Win {
+                    card: 2,
+                    draw: 11,
+                    number: 24,
+                    score: 4512
+                },
+                Win {
+                    card: 0,
+                    draw: 13,
+                    number: 16,
+                    score: 137 * 16
+                },
+                Win {
+                    card: 1,
+                    draw: 14,
+                    number: 13,
+                    score: 1924
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day4_strategy() {
+        let (draws, cards) = parse_input(INPUT);
+        let rules = Rules::default();
+
+        assert_eq!(draws_to_win(&cards[2], &draws, &rules), Some(12));
+        assert_eq!(draws_to_win(&cards[1], &draws, &rules), Some(15));
+        assert_eq!(first_winner(&draws, &cards, &rules), Some(2));
+
+        let reversed: Vec<u64> = draws.iter().rev().copied().collect();
+        let winner = first_winner(&reversed, &cards, &rules).unwrap();
+        assert_eq!(replay(&reversed, &cards, &rules)[0].card, winner);
+
+        // 24 completes the winning row of card 2
+        let removed = draws.iter().position(|x| *x == 24).unwrap();
+        assert_ne!(winner_without(&draws, &cards, &rules, removed), Some(2));
+        let removals = removals_to_win(&draws, &cards, &rules, 2);
+        assert!(!removals.contains(&removed));
+        assert!(removals.contains(&(draws.len() - 1)));
+        for i in removals_to_win(&draws, &cards, &rules, 0) {
+            assert_eq!(winner_without(&draws, &cards, &rules, i), Some(0));
+        }
+    }
 }
@@ -1,7 +1,10 @@
 use aoc_runner_derive::aoc;
 use aoc_runner_derive::aoc_generator;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::From;
+use std::fmt;
+
+use crate::simulation::Frame;
 
 pub type Point = (i64, i64);
 
@@ -15,8 +18,7 @@ impl From<&str> for Segment {
     fn from(raw: &str) -> Self {
         let points: Vec<i64> = raw
             .split(" -> ")
-            .map(|x| x.split(','))
-            .flatten()
+            .flat_map(|x| x.split(','))
             .map(|x| x.parse::<i64>().unwrap())
             .collect();
 
@@ -31,39 +33,117 @@ impl From<&str> for Segment {
 pub enum Dir {
     Horizontal,
     Vertical,
+    /// At exactly 45 degrees
     Diagonal,
+    /// Any other slope
+    Oblique,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
 }
 
+/// Iterator over the lattice points of a segment, from start to end.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Points {
+    next: Point,
+    step: Point,
+    remaining: usize,
+}
+
+impl Iterator for Points {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let point = self.next;
+        self.next = (point.0 + self.step.0, point.1 + self.step.1);
+        self.remaining -= 1;
+        Some(point)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Points {}
+
 impl Segment {
     pub fn direction(&self) -> Dir {
         if self.start.0 == self.end.0 {
             Dir::Vertical
         } else if self.start.1 == self.end.1 {
             Dir::Horizontal
-        } else {
+        } else if (self.end.0 - self.start.0).abs() == (self.end.1 - self.start.1).abs() {
             Dir::Diagonal
+        } else {
+            Dir::Oblique
         }
     }
 
-    pub fn points(&self) -> Vec<Point> {
-        // Rust ranges do not support a negative step, so h_range and v_range
-        // are a dirty way to bypass this.
-        let h_range: Vec<i64> = if self.start.0 < self.end.0 {
-            (self.start.0..=self.end.0).collect()
-        } else {
-            (self.end.0..=self.start.0).rev().collect()
-        };
+    /// Returns the smallest move from one lattice point of the segment to
+    /// the next, and how many of those moves it takes to reach the end.
+    /// Oblique segments may pass between lattice points: only the ones
+    /// they go through exactly count as covered.
+    pub fn step(&self) -> (Point, usize) {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let moves = gcd(dx, dy);
+        if moves == 0 {
+            return ((0, 0), 0);
+        }
+        ((dx / moves, dy / moves), moves as usize)
+    }
 
-        let v_range: Vec<i64> = if self.start.1 < self.end.1 {
-            (self.start.1..=self.end.1).collect()
-        } else {
-            (self.end.1..=self.start.1).rev().collect()
-        };
+    /// Returns whether the segment covers more than its endpoints.
+    pub fn has_inner_points(&self) -> bool {
+        self.step().1 > 1
+    }
+
+    fn rows(&self) -> (i64, i64) {
+        (
+            std::cmp::min(self.start.1, self.end.1),
+            std::cmp::max(self.start.1, self.end.1),
+        )
+    }
+
+    /// Returns the columns covered by the segment at row `y`, which must be
+    /// within the segment's rows. Oblique segments might not go through
+    /// any lattice point in that row.
+    fn columns_at(&self, y: i64) -> Option<(i64, i64)> {
+        if self.direction() == Dir::Horizontal {
+            return Some((
+                std::cmp::min(self.start.0, self.end.0),
+                std::cmp::max(self.start.0, self.end.0),
+            ));
+        }
+
+        let ((dx, dy), moves) = self.step();
+        if moves == 0 {
+            // a single point
+            return Some((self.start.0, self.start.0));
+        }
+        let offset = y - self.start.1;
+        if offset % dy != 0 {
+            return None;
+        }
+        let x = self.start.0 + offset / dy * dx;
+        Some((x, x))
+    }
 
-        match self.direction() {
-            Dir::Vertical => v_range.into_iter().map(|y| (self.start.0, y)).collect(),
-            Dir::Horizontal => h_range.into_iter().map(|x| (x, self.start.1)).collect(),
-            Dir::Diagonal => h_range.into_iter().zip(v_range.into_iter()).collect(),
+    pub fn points(&self) -> Points {
+        let (step, moves) = self.step();
+        Points {
+            next: self.start,
+            step,
+            remaining: moves + 1,
         }
     }
 }
@@ -73,24 +153,212 @@ pub fn parse_input(input: &str) -> Vec<Segment> {
     input.lines().map(Segment::from).collect()
 }
 
-fn solve(vents: &[Segment]) -> u64 {
-    let mut ocean_map = HashMap::new();
+/// How many vents cover each cell of the ocean floor. Cells without any
+/// vent are not stored.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CoverageMap {
+    counts: HashMap<Point, u64>,
+}
+
+impl CoverageMap {
+    pub fn new(vents: &[Segment]) -> Self {
+        let mut counts = HashMap::new();
+        for p in vents.iter().flat_map(|x| x.points()) {
+            *counts.entry(p).or_insert(0) += 1;
+        }
+        Self { counts }
+    }
+
+    pub fn count_at(&self, point: Point) -> u64 {
+        *self.counts.get(&point).unwrap_or(&0)
+    }
+
+    /// Number of cells covered by at least `threshold` vents.
+    pub fn at_least(&self, threshold: u64) -> usize {
+        self.counts.values().filter(|x| **x >= threshold).count()
+    }
+
+    /// Returns the highest coverage, along with the cells that have it
+    /// sorted by row and column.
+    pub fn hottest(&self) -> Option<(u64, Vec<Point>)> {
+        let max = *self.counts.values().max()?;
+        let mut cells: Vec<Point> = self
+            .counts
+            .iter()
+            .filter(|(_, count)| **count == max)
+            .map(|(point, _)| *point)
+            .collect();
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        Some((max, cells))
+    }
+
+    /// Number of cells for each coverage count.
+    pub fn histogram(&self) -> BTreeMap<u64, usize> {
+        let mut histogram = BTreeMap::new();
+        for count in self.counts.values() {
+            *histogram.entry(*count).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Returns the top-left and bottom-right corners of the covered area.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let xs = self.counts.keys().map(|(x, _)| *x);
+        let ys = self.counts.keys().map(|(_, y)| *y);
+        Some((
+            (xs.clone().min()?, ys.clone().min()?),
+            (xs.max()?, ys.max()?),
+        ))
+    }
+
+    /// Renders the map as an image, where the brightest cells are the ones
+    /// with the highest coverage.
+    pub fn to_frame(&self) -> Frame {
+        let ((left, top), (right, bottom)) = self.bounds().unwrap_or(((0, 0), (0, 0)));
+        let max = self.hottest().map_or(1, |(max, _)| max);
+        let (width, height) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+
+        let mut cells = vec![0; width * height];
+        for ((x, y), count) in self.counts.iter() {
+            let i = (y - top) as usize * width + (x - left) as usize;
+            cells[i] = (count * 255 / max) as u8;
+        }
+
+        Frame {
+            width,
+            height,
+            cells,
+            caption: format!("{} cells with overlaps", self.at_least(2)),
+        }
+    }
+}
+
+impl fmt::Display for CoverageMap {
+    /// Draws the map like the puzzle does: `.` for empty cells and the
+    /// count otherwise (`#` when it doesn't fit in a single digit).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((left, top), (right, bottom)) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+
+        for y in top..=bottom {
+            let row: String = (left..=right)
+                .map(|x| match self.count_at((x, y)) {
+                    0 => '.',
+                    count @ 1..=9 => std::char::from_digit(count as u32, 10).unwrap(),
+                    _ => '#',
+                })
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// How to count the cells where vents overlap.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Algorithm {
+    /// Expands every segment into its points and counts them in a map
+    PointMap,
+    /// Sweeps the map row by row, counting overlaps in each row from the
+    /// intervals the segments cover. Rows between two segment starts or
+    /// ends are counted all at once, so long horizontal and vertical
+    /// segments stay cheap, but slanted ones still take a step per row.
+    SweepLine,
+}
+
+fn count_with_point_map(vents: &[Segment]) -> u64 {
+    CoverageMap::new(vents).at_least(2) as u64
+}
+
+/// Counts how many cells of a row are covered by at least two intervals.
+fn count_row_overlaps(intervals: &[(i64, i64)]) -> u64 {
+    // an interval adds coverage at its first column and removes it right
+    // after the last one
+    let mut events: Vec<(i64, i64)> = intervals
+        .iter()
+        .flat_map(|(from, to)| vec![(*from, 1), (to + 1, -1)])
+        .collect();
+    events.sort_unstable();
+
+    let mut count = 0;
+    let mut depth = 0;
+    let mut previous = 0;
+    for (x, delta) in events {
+        if depth > 1 {
+            count += (x - previous) as u64;
+        }
+        depth += delta;
+        previous = x;
+    }
+
+    count
+}
+
+fn count_with_sweep_line(vents: &[Segment]) -> u64 {
+    let mut pending: Vec<&Segment> = vents.iter().collect();
+    pending.sort_by_key(|x| std::cmp::Reverse(x.rows().0));
+    let mut active: Vec<&Segment> = vec![];
+    let mut count = 0;
+    let mut y = match pending.last() {
+        Some(segment) => segment.rows().0,
+        None => return 0,
+    };
+
+    while !pending.is_empty() || !active.is_empty() {
+        if active.is_empty() {
+            // skip the rows without vents
+            y = std::cmp::max(y, pending.last().unwrap().rows().0);
+        }
+        while pending.last().is_some_and(|x| x.rows().0 == y) {
+            active.push(pending.pop().unwrap());
+        }
+
+        let intervals: Vec<(i64, i64)> = active.iter().filter_map(|x| x.columns_at(y)).collect();
+        let overlaps = count_row_overlaps(&intervals);
+
+        // rows only change where a segment starts or ends, unless a slanted
+        // segment is active, since it moves to another column on every row
+        let slanted = active
+            .iter()
+            .any(|x| matches!(x.direction(), Dir::Diagonal | Dir::Oblique));
+        let next = if slanted {
+            y + 1
+        } else {
+            active
+                .iter()
+                .map(|x| x.rows().1 + 1)
+                .chain(pending.last().map(|x| x.rows().0))
+                .min()
+                .unwrap()
+        };
+        count += overlaps * (next - y) as u64;
 
-    for p in vents.iter().map(|x| x.points()).flatten() {
-        *ocean_map.entry(p).or_insert(0) += 1;
+        active.retain(|x| x.rows().1 >= next);
+        y = next;
     }
 
-    ocean_map
-        .values()
-        .fold(0, |total, x| total + if *x > 1 { 1 } else { 0 })
+    count
+}
+
+pub fn count_overlaps(vents: &[Segment], algorithm: Algorithm) -> u64 {
+    match algorithm {
+        Algorithm::PointMap => count_with_point_map(vents),
+        Algorithm::SweepLine => count_with_sweep_line(vents),
+    }
+}
+
+fn solve(vents: &[Segment]) -> u64 {
+    count_overlaps(vents, Algorithm::PointMap)
 }
 
 #[aoc(day5, part1)]
 pub fn solve_part1(input: &[Segment]) -> u64 {
     let vents = input
-        .to_vec()
-        .into_iter()
-        .filter(|x| x.direction() != Dir::Diagonal)
+        .iter()
+        .cloned()
+        .filter(|x| matches!(x.direction(), Dir::Horizontal | Dir::Vertical))
         .collect::<Vec<Segment>>();
     solve(&vents)
 }
@@ -118,23 +386,23 @@ mod tests {
     #[test]
     fn test_day5_segment_points() {
         assert_eq!(
-            Segment::from("0,0 -> 2,2").points(),
+            Segment::from("0,0 -> 2,2").points().collect::<Vec<Point>>(),
             vec![(0, 0), (1, 1), (2, 2)]
         );
         assert_eq!(
-            Segment::from("2,2 -> 0,0").points(),
+            Segment::from("2,2 -> 0,0").points().collect::<Vec<Point>>(),
             vec![(2, 2), (1, 1), (0, 0)]
         );
         assert_eq!(
-            Segment::from("0,0 -> 2,0").points(),
+            Segment::from("0,0 -> 2,0").points().collect::<Vec<Point>>(),
             vec![(0, 0), (1, 0), (2, 0)]
         );
         assert_eq!(
-            Segment::from("2,0 -> 0,0").points(),
+            Segment::from("2,0 -> 0,0").points().collect::<Vec<Point>>(),
             vec![(2, 0), (1, 0), (0, 0)]
         );
         assert_eq!(
-            Segment::from("0,0 -> 0,2").points(),
+            Segment::from("0,0 -> 0,2").points().collect::<Vec<Point>>(),
             vec![(0, 0), (0, 1), (0, 2)]
         );
     }
@@ -161,4 +429,88 @@ mod tests {
         let input = parse_input(INPUT);
         assert_eq!(solve_part2(&input), 12);
     }
+
+    #[test]
+    fn test_day5_sweep_line() {
+        let input = parse_input(INPUT);
+        let straight: Vec<Segment> = input
+            .iter()
+            .cloned()
+            .filter(|x| matches!(x.direction(), Dir::Horizontal | Dir::Vertical))
+            .collect();
+
+        assert_eq!(count_overlaps(&straight, Algorithm::SweepLine), 5);
+        assert_eq!(count_overlaps(&input, Algorithm::SweepLine), 12);
+
+        let vents = parse_input("0,0 -> 1000,0\n500,0 -> 1500,0\n700,3 -> 700,0\n2,5 -> 9,5");
+        assert_eq!(
+            count_overlaps(&vents, Algorithm::SweepLine),
+            count_overlaps(&vents, Algorithm::PointMap)
+        );
+        assert_eq!(count_overlaps(&vents, Algorithm::SweepLine), 501);
+        assert_eq!(count_overlaps(&[], Algorithm::SweepLine), 0);
+
+        let vents = parse_input("1,1 -> 1,1\n0,1 -> 3,1");
+        assert_eq!(count_overlaps(&vents, Algorithm::PointMap), 1);
+        assert_eq!(count_overlaps(&vents, Algorithm::SweepLine), 1);
+
+        // far too long to go through every row
+        let vents = parse_input(
+            "0,0 -> 0,2000000000\n0,10 -> 0,2000000010\n1,5 -> 1,3000000000\n0,7 -> 3,7\n2,0 -> 5,3",
+        );
+        assert_eq!(
+            count_overlaps(&vents, Algorithm::SweepLine),
+            2000000000 - 10 + 1 + 2
+        );
+    }
+
+    #[test]
+    fn test_day5_oblique_segments() {
+        let segment = Segment::from("0,0 -> 6,3");
+        assert_eq!(segment.direction(), Dir::Oblique);
+        assert_eq!(
+            segment.points().collect::<Vec<Point>>(),
+            vec![(0, 0), (2, 1), (4, 2), (6, 3)]
+        );
+
+        let segment = Segment::from("3,1 -> 0,3");
+        assert!(!segment.has_inner_points());
+        assert_eq!(
+            segment.points().collect::<Vec<Point>>(),
+            vec![(3, 1), (0, 3)]
+        );
+
+        assert_eq!(Segment::from("1,1 -> 1,1").points().len(), 1);
+
+        let vents = parse_input("0,0 -> 6,3\n4,0 -> 4,5\n0,1 -> 5,1\n3,1 -> 0,3");
+        assert_eq!(count_overlaps(&vents, Algorithm::PointMap), 4);
+        assert_eq!(count_overlaps(&vents, Algorithm::SweepLine), 4);
+    }
+
+    #[test]
+    fn test_day5_coverage_map() {
+        let map = CoverageMap::new(&parse_input(INPUT));
+
+        assert_eq!(map.at_least(1), 39);
+        assert_eq!(map.at_least(2), 12);
+        assert_eq!(map.at_least(3), 2);
+        assert_eq!(map.at_least(4), 0);
+        assert_eq!(map.hottest(), Some((3, vec![(4, 4), (6, 4)])));
+        assert_eq!(
+            map.histogram().into_iter().collect::<Vec<_>>(),
+            vec![(1, 27), (2, 10), (3, 2)]
+        );
+        assert_eq!(map.bounds(), Some(((0, 0), (9, 9))));
+        assert_eq!(
+            map.to_string(),
+            "1.1....11.\n.111...2..\n..2.1.111.\n...1.2.2..\n.112313211\n...1.2....\n..1...1...\n.1.....1..\n1.......1.\n222111....\n"
+        );
+
+        let frame = map.to_frame();
+        assert_eq!((frame.width, frame.height), (10, 10));
+        assert_eq!(frame.cell_at(4, 4), 255);
+        assert_eq!(frame.cell_at(0, 9), 170);
+        assert_eq!(frame.cell_at(0, 0), 85);
+        assert_eq!(frame.cell_at(1, 0), 0);
+    }
 }
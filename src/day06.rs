@@ -2,6 +2,10 @@ use aoc_runner_derive::aoc_generator;
 use aoc_runner_derive::aoc;
 
 use std::collections::HashMap;
+use std::fmt;
+
+use crate::simulation::{Frame, Simulation};
+use crate::trace::{self, EventKind};
 
 #[aoc_generator(day6)]
 pub fn parse_input(input: &str) -> Vec<u64> {
@@ -25,6 +29,7 @@ fn simulate_fish(fish: i64, n: i64, cache: &mut HashMap<(i64, i64), u64>) -> u64
 
     // save the result in the cache
     cache.insert((fish, n), fish_count);
+    trace::emit(6, EventKind::StateCached);
     fish_count
 }
 
@@ -32,13 +37,425 @@ fn simulation(input: &[u64], n: u64) -> u64 {
     let mut population_count: u64 = 0;
     let mut cache = HashMap::new();
 
-    for fish in input.to_vec().into_iter() {
+    for fish in input.iter().copied() {
         population_count += simulate_fish(fish as i64, n as i64, &mut cache);
     }
 
     population_count
 }
 
+/// Unsigned integer of any size, so populations after a huge number of
+/// days don't overflow. It only supports what the population model needs.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BigUint {
+    /// Base 2^32 digits, least significant first
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn from_u64(value: u64) -> Self {
+        let mut result = Self {
+            digits: vec![value as u32, (value >> 32) as u32],
+        };
+        result.trim();
+        result
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits.len() {
+            0 => Some(0),
+            1 => Some(self.digits[0] as u64),
+            2 => Some(self.digits[0] as u64 | (self.digits[1] as u64) << 32),
+            _ => None,
+        }
+    }
+
+    /// Approximate value, for when precision doesn't matter.
+    pub fn to_f64(&self) -> f64 {
+        self.digits
+            .iter()
+            .rev()
+            .fold(0.0, |total, digit| total * 4294967296.0 + *digit as f64)
+    }
+
+    fn trim(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let len = std::cmp::max(self.digits.len(), other.digits.len());
+        let mut digits = Vec::with_capacity(len + 1);
+        let mut carry = 0;
+        for i in 0..len {
+            let sum = *self.digits.get(i).unwrap_or(&0) as u64
+                + *other.digits.get(i).unwrap_or(&0) as u64
+                + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+
+        let mut result = Self { digits };
+        result.trim();
+        result
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in other.digits.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+
+        let mut result = Self { digits };
+        result.trim();
+        result
+    }
+
+    /// Divides in place by a small number and returns the remainder.
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0;
+        for digit in self.digits.iter_mut().rev() {
+            let value = (remainder as u64) << 32 | *digit as u64;
+            *digit = (value / divisor as u64) as u32;
+            remainder = (value % divisor as u64) as u32;
+        }
+        self.trim();
+        remainder
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // print in chunks of 9 decimal digits, most significant first
+        let mut value = self.clone();
+        let mut chunks = vec![value.div_rem_small(1_000_000_000)];
+        while !value.digits.is_empty() {
+            chunks.push(value.div_rem_small(1_000_000_000));
+        }
+
+        let mut buffer = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            buffer.push_str(&format!("{:09}", chunk));
+        }
+        write!(f, "{}", buffer)
+    }
+}
+
+/// How counts are added and multiplied by the population model.
+trait Arithmetic {
+    type Value: Clone;
+
+    fn value(&self, value: u64) -> Self::Value;
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+struct Exact;
+
+impl Arithmetic for Exact {
+    type Value = BigUint;
+
+    fn value(&self, value: u64) -> BigUint {
+        BigUint::from_u64(value)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.add(b)
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.mul(b)
+    }
+}
+
+struct Modulo(u64);
+
+impl Arithmetic for Modulo {
+    type Value = u64;
+
+    fn value(&self, value: u64) -> u64 {
+        value % self.0
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % self.0 as u128) as u64
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        (*a as u128 * *b as u128 % self.0 as u128) as u64
+    }
+}
+
+type Matrix<T> = Vec<Vec<T>>;
+
+fn matrix_mul<A: Arithmetic>(
+    ops: &A,
+    a: &Matrix<A::Value>,
+    b: &Matrix<A::Value>,
+) -> Matrix<A::Value> {
+    let n = a.len();
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    (0..n).fold(ops.value(0), |total, k| {
+                        ops.add(&total, &ops.mul(&a[i][k], &b[k][j]))
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// How lanternfish reproduce. In the puzzle, a fish gives birth every 7
+/// days, and newborns take 2 extra days for their first cycle.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Lifecycle {
+    pub cycle: usize,
+    pub newborn_delay: usize,
+}
+
+impl Lifecycle {
+    pub const PUZZLE: Self = Self {
+        cycle: 7,
+        newborn_delay: 2,
+    };
+
+    /// Number of different timer values a fish can have.
+    pub fn timers(&self) -> usize {
+        self.cycle + self.newborn_delay
+    }
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self::PUZZLE
+    }
+}
+
+/// Population model that groups fish by their timer. A day is a linear
+/// transformation of the timer counts, so simulating `n` days is raising
+/// its matrix to the `n`th power, which takes O(log n) multiplications.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PopulationModel {
+    lifecycle: Lifecycle,
+    timers: Vec<u64>,
+}
+
+impl PopulationModel {
+    pub fn new(fishes: &[u64], lifecycle: Lifecycle) -> Result<Self, String> {
+        if lifecycle.cycle == 0 {
+            return Err("The reproduction cycle must be at least 1 day".to_string());
+        }
+
+        let mut timers = vec![0; lifecycle.timers()];
+        for fish in fishes {
+            let timer = timers
+                .get_mut(*fish as usize)
+                .ok_or(format!("Invalid timer for this lifecycle: {}", fish))?;
+            *timer += 1;
+        }
+
+        Ok(Self { lifecycle, timers })
+    }
+
+    /// Matrix that turns the timer counts of a day into the next day's.
+    fn day_matrix<A: Arithmetic>(&self, ops: &A) -> Matrix<A::Value> {
+        let n = self.timers.len();
+        let mut matrix = vec![vec![ops.value(0); n]; n];
+        for timer in 1..n {
+            matrix[timer - 1][timer] = ops.value(1);
+        }
+        // fish with timer 0 give birth and start a new cycle
+        matrix[n - 1][0] = ops.value(1);
+        let parent = self.lifecycle.cycle - 1;
+        matrix[parent][0] = ops.add(&matrix[parent][0], &ops.value(1));
+        matrix
+    }
+
+    fn population_with<A: Arithmetic>(&self, ops: &A, days: u64) -> A::Value {
+        let n = self.timers.len();
+        let mut result: Matrix<A::Value> = (0..n)
+            .map(|i| (0..n).map(|j| ops.value((i == j) as u64)).collect())
+            .collect();
+        let mut base = self.day_matrix(ops);
+        let mut exponent = days;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = matrix_mul(ops, &result, &base);
+            }
+            base = matrix_mul(ops, &base, &base);
+            exponent >>= 1;
+        }
+
+        // the population is the sum of every timer count after `days`
+        result.iter().fold(ops.value(0), |total, row| {
+            row.iter().zip(self.timers.iter()).fold(total, |total, (x, count)| {
+                ops.add(&total, &ops.mul(x, &ops.value(*count)))
+            })
+        })
+    }
+
+    /// Exact population after `days`.
+    pub fn population(&self, days: u64) -> BigUint {
+        self.population_with(&Exact, days)
+    }
+
+    /// Population after `days`, modulo `modulus`. It is much faster than
+    /// the exact count for a huge number of days. Returns `None` for a
+    /// modulus of 0.
+    pub fn population_mod(&self, days: u64, modulus: u64) -> Option<u64> {
+        if modulus == 0 {
+            return None;
+        }
+        Some(self.population_with(&Modulo(modulus), days))
+    }
+
+    /// Iterates over the census of every day, from day 0 to `days`.
+    pub fn series(&self, days: u64) -> Series {
+        Series {
+            lifecycle: self.lifecycle,
+            next: Some(Census {
+                day: 0,
+                timers: self.timers.iter().map(|x| BigUint::from_u64(*x)).collect(),
+            }),
+            days,
+        }
+    }
+
+    /// Exports the census of every day as CSV, with the population and
+    /// the count for each timer value.
+    pub fn to_csv(&self, days: u64) -> String {
+        let timers: Vec<String> = (0..self.timers.len()).map(|x| format!("t{}", x)).collect();
+        let mut buffer = format!("day,population,{}\n", timers.join(","));
+        for census in self.series(days) {
+            let counts: Vec<String> = census.timers.iter().map(|x| x.to_string()).collect();
+            buffer.push_str(&format!(
+                "{},{},{}\n",
+                census.day,
+                census.population(),
+                counts.join(",")
+            ));
+        }
+        buffer
+    }
+}
+
+/// Fish count for each timer value on a given day.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Census {
+    pub day: u64,
+    pub timers: Vec<BigUint>,
+}
+
+impl Census {
+    pub fn population(&self) -> BigUint {
+        self.timers
+            .iter()
+            .fold(BigUint::from_u64(0), |total, x| total.add(x))
+    }
+
+    fn next_day(&self, lifecycle: &Lifecycle) -> Self {
+        let mut timers = self.timers.clone();
+        timers.rotate_left(1); // newborn fish get the highest timer
+        let parent = lifecycle.cycle - 1;
+        timers[parent] = timers[parent].add(&self.timers[0]); // parents start a new cycle
+
+        Self {
+            day: self.day + 1,
+            timers,
+        }
+    }
+}
+
+/// Day by day census of a population.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Series {
+    lifecycle: Lifecycle,
+    next: Option<Census>,
+    days: u64,
+}
+
+impl Iterator for Series {
+    type Item = Census;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let census = self.next.take()?;
+        if census.day < self.days {
+            self.next = Some(census.next_day(&self.lifecycle));
+        }
+        Some(census)
+    }
+}
+
+const BAR_WIDTH: usize = 60;
+
+/// Population grouped by the timer of each fish. It's used to visualize the
+/// growth day by day; the solvers use the memoized recursion above.
+#[derive(Debug, PartialEq, Clone)]
+pub struct School {
+    series: Series,
+    census: Census,
+}
+
+impl School {
+    pub fn new(fishes: &[u64]) -> Self {
+        let model = PopulationModel::new(fishes, Lifecycle::default())
+            .unwrap_or_else(|err| panic!("{}", err));
+        let mut series = model.series(u64::MAX);
+        let census = series.next().unwrap();
+
+        Self { series, census }
+    }
+
+    pub fn population(&self) -> BigUint {
+        self.census.population()
+    }
+}
+
+impl Simulation for School {
+    fn step(&mut self) -> bool {
+        match self.series.next() {
+            Some(census) => {
+                self.census = census;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn snapshot(&self) -> Frame {
+        let counts: Vec<f64> = self.census.timers.iter().map(|x| x.to_f64()).collect();
+        let max = counts.iter().copied().fold(0.0, f64::max);
+        let cells = counts
+            .iter()
+            .flat_map(|count| {
+                let len = if max == 0.0 {
+                    0
+                } else {
+                    (count / max * BAR_WIDTH as f64).round() as usize
+                };
+                (0..BAR_WIDTH).map(move |x| if x < len { 255 } else { 0 })
+            })
+            .collect();
+
+        Frame {
+            width: BAR_WIDTH,
+            height: counts.len(),
+            cells,
+            caption: format!("Day {}: {} fish", self.census.day, self.population()),
+        }
+    }
+}
+
 #[aoc(day6, part1)]
 pub fn solve_part1(input: &[u64]) -> u64 {
     simulation(input, 80)
@@ -64,4 +481,79 @@ mod tests {
         let input = vec![3,4,3,1,2];
         assert_eq!(solve_part2(&input), 26984457539);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_day6_school_step() {
+        let mut school = School::new(&[3, 4, 3, 1, 2]);
+        for _ in 0..18 {
+            school.step();
+        }
+        assert_eq!(school.population().to_u64(), Some(26));
+        assert_eq!(school.snapshot().caption, "Day 18: 26 fish");
+    }
+
+    #[test]
+    fn test_day6_big_uint() {
+        let a = BigUint::from_u64(u64::MAX);
+        assert_eq!(a.add(&BigUint::from_u64(1)).to_string(), "18446744073709551616");
+        assert_eq!(a.mul(&a).to_string(), "340282366920938463426481119284349108225");
+        assert_eq!(a.mul(&a).to_u64(), None);
+        assert_eq!(BigUint::from_u64(0).to_string(), "0");
+        assert_eq!(BigUint::from_u64(1_000_000_000).to_u64(), Some(1_000_000_000));
+    }
+
+    #[test]
+    fn test_day6_population_model() {
+        let input = vec![3,4,3,1,2];
+        let model = PopulationModel::new(&input, Lifecycle::default()).unwrap();
+
+        assert_eq!(model.population(18).to_u64(), Some(26));
+        assert_eq!(model.population(80).to_u64(), Some(solve_part1(&input)));
+        assert_eq!(model.population(256).to_u64(), Some(solve_part2(&input)));
+        assert_eq!(model.population_mod(256, 1_000_000_007), Some(26984457539 % 1_000_000_007));
+        assert_eq!(model.population_mod(256, 0), None);
+        assert_eq!(model.population(10_000).to_u64(), None);
+        assert_eq!(
+            model.population_mod(10_000, 1_000_000_007),
+            Some(model.population(10_000).div_rem_small(1_000_000_007) as u64)
+        );
+
+        // with no delay, every fish doubles each cycle
+        let lifecycle = Lifecycle { cycle: 3, newborn_delay: 0 };
+        let model = PopulationModel::new(&[0], lifecycle).unwrap();
+        assert_eq!(model.population(9).to_u64(), Some(8));
+
+        assert!(PopulationModel::new(&[9], Lifecycle::default()).is_err());
+        assert!(PopulationModel::new(&[0], Lifecycle { cycle: 0, newborn_delay: 3 }).is_err());
+    }
+
+    #[test]
+    fn test_day6_series() {
+        let model = PopulationModel::new(&[3,4,3,1,2], Lifecycle::default()).unwrap();
+        let series: Vec<Census> = model.series(18).collect();
+
+        assert_eq!(series.len(), 19);
+        let counts = |census: &Census| -> Vec<u64> {
+            census.timers.iter().map(|x| x.to_u64().unwrap()).collect()
+        };
+        assert_eq!(counts(&series[0]), vec![0, 1, 1, 2, 1, 0, 0, 0, 0]);
+        assert_eq!(counts(&series[3]), vec![2, 1, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(series[18].population().to_u64(), Some(26));
+        for (day, census) in series.iter().enumerate() {
+            assert_eq!(census.day, day as u64);
+            assert_eq!(census.population(), model.population(day as u64));
+        }
+
+        let csv = model.to_csv(1);
+        assert_eq!(
+            csv,
+            "day,population,t0,t1,t2,t3,t4,t5,t6,t7,t8\n0,5,0,1,1,2,1,0,0,0,0\n1,5,1,1,2,1,0,0,0,0,0\n"
+        );
+
+        // counts don't fit in a u64 long before day 1000
+        let last = model.series(1000).last().unwrap();
+        assert_eq!(last.day, 1000);
+        assert_eq!(last.population(), model.population(1000));
+        assert_eq!(model.to_csv(1000).lines().count(), 1002);
+    }
+}
@@ -1,6 +1,8 @@
 use aoc_runner_derive::aoc;
 use aoc_runner_derive::aoc_generator;
 
+use std::ops::RangeInclusive;
+
 #[aoc_generator(day7)]
 pub fn parse_input(input: &str) -> Vec<u64> {
     input
@@ -10,35 +12,353 @@ pub fn parse_input(input: &str) -> Vec<u64> {
 }
 
 fn distance(a: u64, b: u64) -> u64 {
-    (a as i64 - b as i64).abs() as u64
+    (a as i64 - b as i64).unsigned_abs()
 }
 
 fn compounded_distance(a: u64, b: u64) -> u64 {
-    (0..=distance(a, b)).sum()
+    let d = distance(a, b);
+    d * (d + 1) / 2
+}
+
+/// Fuel a crab burns to move a given distance.
+#[derive(Debug, Clone, Copy)]
+pub enum FuelCost {
+    /// One unit per step
+    Constant,
+    /// One more unit for each extra step
+    Increasing,
+    /// Any other cost. It must be convex, or the optimum found by the fast
+    /// strategies might not be the real one.
+    Convex(fn(u64) -> u64),
+}
+
+impl FuelCost {
+    pub fn fuel(&self, from: u64, to: u64) -> u64 {
+        match self {
+            Self::Constant => distance(from, to),
+            Self::Increasing => compounded_distance(from, to),
+            Self::Convex(f) => f(distance(from, to)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    /// Tries every position between the crabs
+    Scan,
+    /// Uses the median for constant costs and the mean for increasing
+    /// ones. Other costs use a ternary search.
+    Shortcut,
+    /// Narrows down the optimum, which works because the total cost of a
+    /// convex fuel cost is convex as well
+    TernarySearch,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Alignment {
+    pub position: u64,
+    pub cost: u64,
+    /// Every position with the optimal cost, `position` included
+    pub ties: RangeInclusive<u64>,
+}
+
+/// A crab and how many units of fuel it burns per unit of cost, so less
+/// efficient crabs weigh more.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Crab {
+    pub position: u64,
+    pub weight: u64,
 }
 
-fn solver(crabs: &[u64], distance_fn: fn(u64, u64) -> u64) -> u64 {
-    let min_x = crabs.iter().min().copied().unwrap() as u64;
-    let max_x = crabs.iter().max().copied().unwrap() as u64;
+impl Crab {
+    pub fn new(position: u64) -> Self {
+        Self {
+            position,
+            weight: 1,
+        }
+    }
+}
+
+fn total_cost(crabs: &[Crab], cost: FuelCost, x: u64) -> u64 {
+    crabs.iter().fold(0, |total, crab| {
+        total + crab.weight * cost.fuel(crab.position, x)
+    })
+}
+
+fn ternary_search<F>(lo: u64, hi: u64, f: F) -> u64
+where
+    F: Fn(u64) -> u64,
+{
+    let (mut lo, mut hi) = (lo, hi);
+    while hi - lo > 2 {
+        let m1 = lo + (hi - lo) / 3;
+        let m2 = hi - (hi - lo) / 3;
+        let (f1, f2) = (f(m1), f(m2));
+        if f1 < f2 {
+            hi = m2 - 1;
+        } else if f1 > f2 {
+            lo = m1 + 1;
+        } else {
+            // a convex function is flat or has its minimum in between
+            lo = m1;
+            hi = m2;
+        }
+    }
+
+    (lo..=hi).min_by_key(|x| f(*x)).unwrap()
+}
+
+/// Returns the first value of `lo..=hi` that matches `pred`, which must
+/// match `hi` and every value after the first match.
+fn first_where<F>(lo: u64, hi: u64, pred: F) -> u64
+where
+    F: Fn(u64) -> bool,
+{
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    lo
+}
 
-    (min_x..=max_x)
-        .map(|x| {
-            crabs
+fn best_position(crabs: &[Crab], cost: FuelCost, strategy: Strategy, lo: u64, hi: u64) -> u64 {
+    let f = |x| total_cost(crabs, cost, x);
+    let total_weight: u64 = crabs.iter().map(|x| x.weight).sum();
+
+    match (strategy, cost) {
+        (Strategy::Scan, _) => (lo..=hi).min_by_key(|x| f(*x)).unwrap(),
+        (Strategy::Shortcut, FuelCost::Constant) => {
+            let mut sorted = crabs.to_vec();
+            sorted.sort_unstable_by_key(|x| x.position);
+            // weighted median: the first crab with half the weight behind
+            let mut weight = 0;
+            sorted
                 .iter()
-                .fold(0, |total, crab| total + distance_fn(*crab, x))
+                .find(|crab| {
+                    weight += crab.weight;
+                    weight * 2 >= total_weight
+                })
+                .map_or(lo, |crab| crab.position)
+        }
+        (Strategy::Shortcut, FuelCost::Increasing) => {
+            // the optimum is less than half a step away from the mean
+            let sum: u64 = crabs.iter().map(|x| x.position * x.weight).sum();
+            let mean = sum / std::cmp::max(total_weight, 1);
+            (mean.saturating_sub(1)..=mean + 2)
+                .filter(|x| (lo..=hi).contains(x))
+                .min_by_key(|x| f(*x))
+                .unwrap()
+        }
+        _ => ternary_search(lo, hi, f),
+    }
+}
+
+/// Finds the position where aligning the crabs costs the least fuel.
+pub fn align(crabs: &[u64], cost: FuelCost, strategy: Strategy) -> Option<Alignment> {
+    let crabs: Vec<Crab> = crabs.iter().copied().map(Crab::new).collect();
+    align_weighted(&crabs, cost, strategy)
+}
+
+pub fn align_weighted(crabs: &[Crab], cost: FuelCost, strategy: Strategy) -> Option<Alignment> {
+    let lo = crabs.iter().map(|x| x.position).min()?;
+    let hi = crabs.iter().map(|x| x.position).max()?;
+    let f = |x| total_cost(crabs, cost, x);
+
+    let position = best_position(crabs, cost, strategy, lo, hi);
+    let best = f(position);
+
+    // positions with the same cost are next to each other, since the total
+    // cost is convex, so both ends can be found with a binary search
+    let first = first_where(lo, position, |x| f(x) == best);
+    let last = first_where(position, hi, |x| x == hi || f(x + 1) != best);
+
+    Some(Alignment {
+        position: first,
+        cost: best,
+        ties: first..=last,
+    })
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rally {
+    pub cost: u64,
+    /// Where each group of crabs meets, from left to right
+    pub targets: Vec<u64>,
+    /// Indices of the crabs in each group
+    pub groups: Vec<Vec<usize>>,
+}
+
+/// Fuel to align a run of crabs, sorted by position and with distinct
+/// positions. Prefix sums of the weights (and of their first and second
+/// moments) give the puzzle costs in O(log n) for any target, while other
+/// costs are added up crab by crab.
+struct RunCost<'a> {
+    crabs: &'a [Crab],
+    cost: FuelCost,
+    // sums[i] -> (Σw, Σw·p, Σw·p²) of the first i crabs
+    sums: Vec<(i128, i128, i128)>,
+}
+
+impl<'a> RunCost<'a> {
+    fn new(crabs: &'a [Crab], cost: FuelCost) -> Self {
+        let mut sums = vec![(0, 0, 0)];
+        for crab in crabs {
+            let (w, p) = (crab.weight as i128, crab.position as i128);
+            let (sw, swp, swp2) = *sums.last().unwrap();
+            sums.push((sw + w, swp + w * p, swp2 + w * p * p));
+        }
+        Self { crabs, cost, sums }
+    }
+
+    fn between(&self, i: usize, j: usize) -> (i128, i128, i128) {
+        let (a, b) = (self.sums[i], self.sums[j]);
+        (b.0 - a.0, b.1 - a.1, b.2 - a.2)
+    }
+
+    /// Fuel for crabs `i..j` to meet at `x`
+    fn at(&self, i: usize, j: usize, x: u64) -> u64 {
+        let m = i + self.crabs[i..j].partition_point(|crab| crab.position <= x);
+        let (lw, lp, lp2) = self.between(i, m);
+        let (rw, rp, rp2) = self.between(m, j);
+        let x = x as i128;
+
+        let total = match self.cost {
+            FuelCost::Constant => (x * lw - lp) + (rp - x * rw),
+            // Σw·d·(d + 1)/2, with d = x - p on the left and p - x on the right
+            FuelCost::Increasing => {
+                let left = x * x * lw - 2 * x * lp + lp2 + x * lw - lp;
+                let right = rp2 - 2 * x * rp + x * x * rw + rp - x * rw;
+                (left + right) / 2
+            }
+            FuelCost::Convex(_) => return total_cost(&self.crabs[i..j], self.cost, x as u64),
+        };
+        total as u64
+    }
+
+    /// Returns the cost and the target of the best alignment for crabs
+    /// `i..j`, the leftmost target if there are ties.
+    fn best(&self, i: usize, j: usize) -> (u64, u64) {
+        let (lo, hi) = (self.crabs[i].position, self.crabs[j - 1].position);
+        let f = |x| self.at(i, j, x);
+        let (weight, moment, _) = self.between(i, j);
+
+        let target = match self.cost {
+            FuelCost::Constant => {
+                // weighted median: the first crab with half the weight behind
+                let behind = |sums: &(i128, i128, i128)| (sums.0 - self.sums[i].0) * 2;
+                let m = self.sums[i + 1..=j].partition_point(|x| behind(x) < weight);
+                self.crabs[i + m].position
+            }
+            FuelCost::Increasing => {
+                // the optimum is less than half a step away from the mean
+                let mean = (moment / weight) as u64;
+                (mean.saturating_sub(1)..=mean + 2)
+                    .filter(|x| (lo..=hi).contains(x))
+                    .min_by_key(|x| f(*x))
+                    .unwrap()
+            }
+            FuelCost::Convex(_) => {
+                let x = ternary_search(lo, hi, f);
+                let cost = f(x);
+                first_where(lo, x, |x| f(x) == cost)
+            }
+        };
+        (f(target), target)
+    }
+}
+
+/// Splits the crabs into (at most) `k` groups, each one aligning at its own
+/// target, so the total fuel is minimal. With a convex cost, the crabs of
+/// a group are next to each other once sorted, so the best split is found
+/// exactly with dynamic programming over the sorted crabs. Crabs at the same
+/// position are merged first. For `n` distinct positions, the split takes
+/// O(k·n²) steps, each of them aligning a run of positions. That is O(log n)
+/// for the puzzle costs, but other costs go through every crab of the run
+/// for each target they try.
+pub fn rally(crabs: &[Crab], k: usize, cost: FuelCost) -> Option<Rally> {
+    if crabs.is_empty() || k == 0 {
+        return None;
+    }
+
+    let mut positions: Vec<Crab> = vec![];
+    let mut sorted: Vec<usize> = (0..crabs.len()).collect();
+    sorted.sort_by_key(|i| crabs[*i].position);
+    for i in sorted.iter() {
+        match positions.last_mut() {
+            Some(last) if last.position == crabs[*i].position => last.weight += crabs[*i].weight,
+            _ => positions.push(crabs[*i]),
+        }
+    }
+
+    let n = positions.len();
+    let k = std::cmp::min(k, n);
+    let run_cost = RunCost::new(&positions, cost);
+    let segment_cost = |i: usize, j: usize| run_cost.best(i, j).0;
+
+    // cost_table[g][j] -> cheapest way to split the first j positions in g groups,
+    // along with where the last group starts
+    let mut cost_table = vec![vec![(u64::MAX, 0); n + 1]; k + 1];
+    cost_table[0][0] = (0, 0);
+    for g in 1..=k {
+        for j in g..=n {
+            cost_table[g][j] = (g - 1..j)
+                .filter(|i| cost_table[g - 1][*i].0 != u64::MAX)
+                .map(|i| (cost_table[g - 1][i].0 + segment_cost(i, j), i))
+                .min()
+                .unwrap();
+        }
+    }
+
+    // walk the table back to find the groups
+    let mut bounds = vec![];
+    let mut j = n;
+    for g in (1..=k).rev() {
+        let i = cost_table[g][j].1;
+        bounds.push((i, j));
+        j = i;
+    }
+    bounds.reverse();
+
+    let targets: Vec<u64> = bounds
+        .iter()
+        .map(|(i, j)| run_cost.best(*i, *j).1)
+        .collect();
+    let groups = bounds
+        .iter()
+        .map(|(i, j)| {
+            let (from, to) = (positions[*i].position, positions[*j - 1].position);
+            sorted
+                .iter()
+                .copied()
+                .filter(|x| (from..=to).contains(&crabs[*x].position))
+                .collect()
         })
-        .min()
-        .unwrap()
+        .collect();
+
+    Some(Rally {
+        cost: cost_table[k][n].0,
+        targets,
+        groups,
+    })
 }
 
 #[aoc(day7, part1)]
 pub fn solve_part1(crabs: &[u64]) -> u64 {
-    solver(crabs, distance)
+    align(crabs, FuelCost::Constant, Strategy::Shortcut)
+        .unwrap()
+        .cost
 }
 
 #[aoc(day7, part2)]
 pub fn solve_part2(crabs: &[u64]) -> u64 {
-    solver(crabs, compounded_distance)
+    align(crabs, FuelCost::Increasing, Strategy::Shortcut)
+        .unwrap()
+        .cost
 }
 
 #[cfg(test)]
@@ -64,4 +384,144 @@ mod tests {
         let input: Vec<u64> = vec![16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
         assert_eq!(solve_part2(&input), 168);
     }
+
+    #[test]
+    fn test_day7_align() {
+        let input: Vec<u64> = vec![16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+        let cubic = FuelCost::Convex(|d| d * d * d);
+        let strategies = [Strategy::Scan, Strategy::Shortcut, Strategy::TernarySearch];
+
+        for strategy in strategies.iter().copied() {
+            let alignment = align(&input, FuelCost::Constant, strategy).unwrap();
+            assert_eq!((alignment.position, alignment.cost), (2, 37));
+
+            let alignment = align(&input, FuelCost::Increasing, strategy).unwrap();
+            assert_eq!((alignment.position, alignment.cost), (5, 168));
+
+            assert_eq!(
+                align(&input, cubic, strategy),
+                align(&input, cubic, Strategy::Scan)
+            );
+        }
+        assert_eq!(align(&[], FuelCost::Constant, Strategy::Scan), None);
+    }
+
+    #[test]
+    fn test_day7_align_ties() {
+        let input: Vec<u64> = vec![0, 10];
+        let expected = Alignment {
+            position: 0,
+            cost: 10,
+            ties: 0..=10,
+        };
+
+        assert_eq!(
+            align(&input, FuelCost::Constant, Strategy::Shortcut),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            align(&input, FuelCost::Constant, Strategy::TernarySearch),
+            Some(expected)
+        );
+        assert_eq!(
+            align(&[1, 2], FuelCost::Increasing, Strategy::Shortcut)
+                .unwrap()
+                .ties,
+            1..=2
+        );
+
+        // far apart, so the ties can't be walked one by one
+        let alignment = align(&[0, 20_000_000], FuelCost::Constant, Strategy::Shortcut).unwrap();
+        assert_eq!(alignment.ties, 0..=20_000_000);
+        let alignment = align(&[3, 3, 9], FuelCost::Increasing, Strategy::Scan).unwrap();
+        assert_eq!(alignment.ties, 5..=5);
+    }
+
+    #[test]
+    fn test_day7_weighted_crabs() {
+        let crabs = vec![
+            Crab {
+                position: 0,
+                weight: 3,
+            },
+            Crab::new(10),
+        ];
+
+        for strategy in [Strategy::Scan, Strategy::Shortcut, Strategy::TernarySearch].iter() {
+            let alignment = align_weighted(&crabs, FuelCost::Constant, *strategy).unwrap();
+            assert_eq!((alignment.position, alignment.cost), (0, 10));
+
+            // 3 * (2 + 1) + (8 * 9 / 2) = 45
+            let alignment = align_weighted(&crabs, FuelCost::Increasing, *strategy).unwrap();
+            assert_eq!((alignment.position, alignment.cost), (2, 45));
+        }
+    }
+
+    #[test]
+    fn test_day7_rally() {
+        let crabs: Vec<Crab> = [1, 2, 3, 100, 101, 200]
+            .iter()
+            .copied()
+            .map(Crab::new)
+            .collect();
+
+        let one = rally(&crabs, 1, FuelCost::Constant).unwrap();
+        assert_eq!(
+            Some(one.cost),
+            align(
+                &[1, 2, 3, 100, 101, 200],
+                FuelCost::Constant,
+                Strategy::Scan
+            )
+            .map(|x| x.cost)
+        );
+
+        let three = rally(&crabs, 3, FuelCost::Constant).unwrap();
+        assert_eq!(
+            three,
+            Rally {
+                cost: 3,
+                targets: vec![2, 100, 200],
+                groups: vec![vec![0, 1, 2], vec![3, 4], vec![5]],
+            }
+        );
+
+        let all = rally(&crabs, 10, FuelCost::Increasing).unwrap();
+        assert_eq!(all.cost, 0);
+        assert_eq!(all.targets.len(), 6);
+
+        let input: Vec<Crab> = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14]
+            .iter()
+            .copied()
+            .map(Crab::new)
+            .collect();
+        let two = rally(&input, 2, FuelCost::Constant).unwrap();
+        assert_eq!(two.targets, vec![2, 14]);
+        assert_eq!(two.groups[1], vec![9, 0]);
+        assert!(rally(&[], 2, FuelCost::Constant).is_none());
+
+        // targets are computed, not searched for among the coordinates
+        let far: Vec<Crab> = [0, 40_000_000].iter().copied().map(Crab::new).collect();
+        let one = rally(&far, 1, FuelCost::Increasing).unwrap();
+        assert_eq!(
+            (one.cost, one.targets),
+            (400_000_020_000_000, vec![20_000_000])
+        );
+
+        // a single group agrees with `align`, whatever the cost
+        let scattered: Vec<u64> = (0..200).map(|x| x * 7919 % 1000).collect();
+        let crabs: Vec<Crab> = scattered.iter().copied().map(Crab::new).collect();
+        for cost in [
+            FuelCost::Constant,
+            FuelCost::Increasing,
+            FuelCost::Convex(|d| d * d),
+        ] {
+            let one = rally(&crabs, 1, cost).unwrap();
+            let alignment = align(&scattered, cost, Strategy::Scan).unwrap();
+            assert_eq!(
+                (one.cost, one.targets),
+                (alignment.cost, vec![alignment.position])
+            );
+        }
+    }
 }
@@ -3,6 +3,7 @@ use aoc_runner_derive::aoc_generator;
 use itertools::Itertools;
 
 use std::collections::HashMap;
+use std::fmt;
 
 type SegmentsDisplay = (Vec<String>, Vec<String>);
 
@@ -34,111 +35,542 @@ pub fn solve_part1(input: &[SegmentsDisplay]) -> u64 {
     let targets = [2, 4, 3, 7];
     input
         .iter()
-        .map(|(_, output)| output.clone())
-        .flatten()
+        .flat_map(|(_, output)| output.clone())
         .fold(0, |total, x| {
             total + if targets.contains(&x.len()) { 1 } else { 0 }
         })
 }
 
-fn named_segments_frequency(signals: &[String]) -> HashMap<char, u64> {
-    let mut res = HashMap::new();
+/// Segments lit for each digit, as a bitmask where bit 0 is segment `a`.
+const DIGITS: [u32; 10] = [
+    0b1110111, // 0 -> abcefg
+    0b0100100, // 1 -> cf
+    0b1011101, // 2 -> acdeg
+    0b1101101, // 3 -> acdfg
+    0b0101110, // 4 -> bcdf
+    0b1101011, // 5 -> abdfg
+    0b1111011, // 6 -> abdefg
+    0b0100101, // 7 -> acf
+    0b1111111, // 8 -> abcdefg
+    0b1101111, // 9 -> abcdfg
+];
 
-    for name in signals.join("").chars() {
-        *res.entry(name).or_insert(0) += 1;
+/// Extra glyphs for hexadecimal digits, shown as `AbCdEF`.
+const HEX_LETTERS: [(char, u32); 6] = [
+    ('A', 0b0111111), // abcdef
+    ('b', 0b1111010), // bdefg
+    ('C', 0b1010011), // abeg
+    ('d', 0b1111100), // cdefg
+    ('E', 0b1011011), // abdeg
+    ('F', 0b0011011), // abde
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecodeError {
+    InvalidAlphabet(String),
+    InvalidPattern(String),
+    NoSolution,
+    /// The wiring can't be told apart, and the output could be any of these
+    Ambiguous(Vec<String>),
+    /// More than one wiring fits, even if they all decode the same output
+    AmbiguousWiring(usize),
+    EmptyOutput,
+    /// The output digits don't fit in a `u64`
+    Overflow(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAlphabet(reason) => write!(f, "Invalid alphabet: {}", reason),
+            Self::InvalidPattern(pattern) => write!(f, "Invalid pattern: {}", pattern),
+            Self::NoSolution => write!(f, "No wiring matches the patterns"),
+            Self::Ambiguous(values) => write!(f, "Ambiguous output: {:?}", values),
+            Self::AmbiguousWiring(count) => write!(f, "{} wirings fit the patterns", count),
+            Self::EmptyOutput => write!(f, "No output to decode"),
+            Self::Overflow(digits) => write!(f, "Output too big: {}", digits),
+        }
     }
+}
 
-    res
+/// The glyphs a display can show, and which of its segments each one
+/// lights. Segments are named with letters from `a` onwards.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Alphabet {
+    segments: usize,
+    glyphs: Vec<(char, u32)>,
 }
 
-fn named_segment_with_freq(freqs: &HashMap<char, u64>, target: u64) -> char {
-    freqs
-        .iter()
-        .find_map(|(name, &freq)| if freq == target { Some(name) } else { None })
-        .unwrap()
-        .to_owned()
-}
-
-fn decode_signals(signals: &[String]) -> HashMap<String, u64> {
-    let mut digits: [&str; 10] = [""; 10];
-    let mut segs: [char; 7] = ['*'; 7]; // segments: 0 -> unmangled(a), 1 -> unmangled(b)...
-
-    // 1, 4, 7, 8 are unique, so fill those first
-    digits[1] = signals.iter().find(|x| x.len() == 2).unwrap();
-    digits[8] = signals.iter().find(|x| x.len() == 7).unwrap();
-    digits[4] = signals.iter().find(|x| x.len() == 4).unwrap();
-    digits[7] = signals.iter().find(|x| x.len() == 3).unwrap();
-
-    // Find segments with unique freq count
-    let freqs = named_segments_frequency(signals);
-    segs[1] = named_segment_with_freq(&freqs, 6); // B
-    segs[4] = named_segment_with_freq(&freqs, 4); // E
-    segs[5] = named_segment_with_freq(&freqs, 9); // F
-    segs[2] = digits[1].chars().find(|x| *x != segs[5]).unwrap(); // C
-    segs[3] = digits[4]
-        .chars()
-        .find(|x| ![segs[1], segs[2], segs[5]].contains(x))
-        .unwrap(); // D
-    segs[0] = digits[7]
-        .chars()
-        .find(|x| ![segs[2], segs[5]].contains(x))
-        .unwrap(); // A
-    segs[6] = digits[8]
-        .chars()
-        .find(|x| !segs.contains(x)) // only remaining unknown segment
-        .unwrap(); // G
+impl Alphabet {
+    /// Defines an alphabet from the segments of each glyph, e.g.
+    /// `('1', "cf")`. Every glyph must light a different set of segments.
+    pub fn new(segments: usize, glyphs: &[(char, &str)]) -> Result<Self, DecodeError> {
+        if segments == 0 || segments > 26 {
+            return Err(DecodeError::InvalidAlphabet(format!(
+                "{} segments",
+                segments
+            )));
+        }
 
-    // Digit 2 is the only one without segment 'f'
-    digits[2] = signals.iter().find(|x| !x.contains(segs[5])).unwrap();
-    // Digit 3 is the only one with len(5) and segments 'cf'
-    digits[3] = signals
-        .iter()
-        .filter(|x| x.len() == 5)
-        .find(|x| x.contains(segs[2]) && x.contains(segs[5]))
-        .unwrap();
-    // Digit 9 is the only one with len(6) and missing segment e
-    digits[9] = signals
-        .iter()
-        .filter(|x| x.len() == 6)
-        .find(|x| !x.contains(segs[4]))
-        .unwrap();
-    // Digit 5 is the only one with len(5) and missing segments c & e
-    digits[5] = signals
+        let mut alphabet = Self {
+            segments,
+            glyphs: vec![],
+        };
+        for (glyph, lit) in glyphs {
+            let mask = pattern_mask(lit, segments)
+                .map_err(|_| DecodeError::InvalidAlphabet(format!("{} -> {}", glyph, lit)))?;
+            if let Some(other) = alphabet.glyph(mask) {
+                return Err(DecodeError::InvalidAlphabet(format!(
+                    "{} and {} look the same",
+                    other, glyph
+                )));
+            }
+            alphabet.glyphs.push((*glyph, mask));
+        }
+
+        Ok(alphabet)
+    }
+
+    /// Digits on a seven-segment display, like in the puzzle.
+    pub fn digits() -> Self {
+        Self {
+            segments: 7,
+            glyphs: DIGITS
+                .iter()
+                .enumerate()
+                .map(|(i, mask)| (std::char::from_digit(i as u32, 10).unwrap(), *mask))
+                .collect(),
+        }
+    }
+
+    /// Hexadecimal digits on a seven-segment display.
+    pub fn hex() -> Self {
+        let mut alphabet = Self::digits();
+        alphabet.glyphs.extend_from_slice(&HEX_LETTERS);
+        alphabet
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    fn glyph(&self, segments: u32) -> Option<char> {
+        self.glyphs
+            .iter()
+            .find(|(_, mask)| *mask == segments)
+            .map(|(glyph, _)| *glyph)
+    }
+
+    fn masks(&self) -> Vec<u32> {
+        self.glyphs.iter().map(|(_, mask)| *mask).collect()
+    }
+}
+
+fn pattern_mask(pattern: &str, segments: usize) -> Result<u32, DecodeError> {
+    let mut mask = 0;
+    for wire in pattern.chars() {
+        let index = (wire as u32).wrapping_sub('a' as u32);
+        if index >= segments as u32 {
+            return Err(DecodeError::InvalidPattern(pattern.to_string()));
+        }
+        let bit = 1 << index;
+        if mask & bit != 0 {
+            return Err(DecodeError::InvalidPattern(pattern.to_string()));
+        }
+        mask |= bit;
+    }
+    Ok(mask)
+}
+
+/// Which segment each (scrambled) wire is connected to.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Wiring {
+    segments: Vec<usize>,
+}
+
+impl Wiring {
+    pub fn segment_for(&self, wire: char) -> Option<char> {
+        let segment = self
+            .segments
+            .get((wire as u32).checked_sub('a' as u32)? as usize)?;
+        std::char::from_u32('a' as u32 + *segment as u32)
+    }
+
+    /// Returns the segments lit by a pattern of wires.
+    fn translate(&self, wires: u32) -> u32 {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(|(wire, _)| wires & (1 << wire) != 0)
+            .fold(0, |mask, (_, segment)| mask | (1 << segment))
+    }
+
+    pub fn glyph_for(&self, pattern: &str, alphabet: &Alphabet) -> Option<char> {
+        let wires = pattern_mask(pattern, self.segments.len()).ok()?;
+        alphabet.glyph(self.translate(wires))
+    }
+
+    pub fn digit_for(&self, pattern: &str) -> Option<u64> {
+        let glyph = self.glyph_for(pattern, &Alphabet::digits())?;
+        glyph.to_digit(10).map(|x| x as u64)
+    }
+}
+
+impl fmt::Display for Wiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = (0..self.segments.len())
+            .map(|wire| {
+                let wire = std::char::from_u32('a' as u32 + wire as u32).unwrap();
+                format!("{}->{}", wire, self.segment_for(wire).unwrap())
+            })
+            .collect();
+        write!(f, "{}", pairs.join(" "))
+    }
+}
+
+/// Checks whether a partial wiring (`None` for wires not assigned yet) can
+/// still turn every pattern into a glyph. For a pattern to match a glyph,
+/// its assigned wires must light segments of the glyph, and the assigned
+/// wires not in the pattern must light segments out of it.
+fn is_consistent(assigned: &[Option<usize>], patterns: &[u32], glyphs: &[u32]) -> bool {
+    patterns.iter().all(|pattern| {
+        let (mut lit, mut unlit) = (0, 0);
+        for (wire, segment) in assigned.iter().enumerate() {
+            if let Some(segment) = segment {
+                if pattern & (1 << wire) != 0 {
+                    lit |= 1 << segment;
+                } else {
+                    unlit |= 1 << segment;
+                }
+            }
+        }
+
+        glyphs.iter().any(|glyph| {
+            glyph.count_ones() == pattern.count_ones() && lit & !glyph == 0 && unlit & glyph == 0
+        })
+    })
+}
+
+/// Calls `visit` with each wiring that fits, until it returns `false`, and
+/// returns whether the search should go on. A wire with a twin (an earlier
+/// wire that is in exactly the same patterns) only takes segments after
+/// the twin's, which skips wirings that just swap the two.
+fn search_wirings<F>(
+    assigned: &mut Vec<Option<usize>>,
+    wire: usize,
+    patterns: &[u32],
+    glyphs: &[u32],
+    twins: &[Option<usize>],
+    visit: &mut F,
+) -> bool
+where
+    F: FnMut(Wiring) -> bool,
+{
+    if wire == assigned.len() {
+        return visit(Wiring {
+            segments: assigned.iter().map(|x| x.unwrap()).collect(),
+        });
+    }
+
+    let first = twins[wire].map_or(0, |twin| assigned[twin].unwrap() + 1);
+    for segment in first..assigned.len() {
+        if assigned.contains(&Some(segment)) {
+            continue;
+        }
+        assigned[wire] = Some(segment);
+        let go_on = !is_consistent(assigned, patterns, glyphs)
+            || search_wirings(assigned, wire + 1, patterns, glyphs, twins, visit);
+        assigned[wire] = None;
+        if !go_on {
+            return false;
+        }
+    }
+    true
+}
+
+fn pattern_masks(patterns: &[String], alphabet: &Alphabet) -> Result<Vec<u32>, DecodeError> {
+    let mut masks = patterns
         .iter()
-        .filter(|x| x.len() == 5)
-        .find(|x| !(x.contains(segs[4]) || x.contains(segs[2])))
-        .unwrap();
-    // Digit 0 is the only one with len(6) and missing segment d
-    digits[0] = signals
+        .map(|x| pattern_mask(x, alphabet.segments))
+        .collect::<Result<Vec<u32>, DecodeError>>()?;
+    masks.sort_unstable();
+    masks.dedup();
+    Ok(masks)
+}
+
+/// Calls `visit` with every wiring that turns all the patterns into glyphs
+/// of the alphabet, until it returns `false`. Wirings are found by
+/// assigning a segment to one wire at a time and backtracking as soon as a
+/// pattern can't be a glyph anymore.
+pub fn for_each_wiring_for<F>(
+    patterns: &[String],
+    alphabet: &Alphabet,
+    mut visit: F,
+) -> Result<(), DecodeError>
+where
+    F: FnMut(Wiring) -> bool,
+{
+    let masks = pattern_masks(patterns, alphabet)?;
+    search_wirings(
+        &mut vec![None; alphabet.segments],
+        0,
+        &masks,
+        &alphabet.masks(),
+        &vec![None; alphabet.segments],
+        &mut visit,
+    );
+    Ok(())
+}
+
+/// Finds every wiring that fits the patterns. Patterns don't need to
+/// include every glyph: with fewer of them, more than one wiring might fit.
+/// Displays with many segments can then have millions of them, so
+/// `for_each_wiring_for` can stop the search instead of collecting them.
+pub fn solve_wiring_for(
+    patterns: &[String],
+    alphabet: &Alphabet,
+) -> Result<Vec<Wiring>, DecodeError> {
+    let mut solutions = vec![];
+    for_each_wiring_for(patterns, alphabet, |wiring| {
+        solutions.push(wiring);
+        true
+    })?;
+
+    if solutions.is_empty() {
+        return Err(DecodeError::NoSolution);
+    }
+    Ok(solutions)
+}
+
+pub fn solve_wiring(patterns: &[String]) -> Result<Vec<Wiring>, DecodeError> {
+    solve_wiring_for(patterns, &Alphabet::digits())
+}
+
+/// Returns one wiring for each way of decoding the patterns, along with how
+/// many wirings decode them like each one does. Wires that are in exactly
+/// the same patterns can be swapped around without changing any glyph, so
+/// only one order of them is searched.
+fn distinct_wirings(
+    patterns: &[String],
+    alphabet: &Alphabet,
+) -> Result<(Vec<Wiring>, usize), DecodeError> {
+    let masks = pattern_masks(patterns, alphabet)?;
+    let in_patterns =
+        |wire: usize| -> Vec<bool> { masks.iter().map(|x| x & (1 << wire) != 0).collect() };
+    let twins: Vec<Option<usize>> = (0..alphabet.segments)
+        .map(|wire| {
+            (0..wire)
+                .rev()
+                .find(|x| in_patterns(*x) == in_patterns(wire))
+        })
+        .collect();
+
+    let mut solutions = vec![];
+    search_wirings(
+        &mut vec![None; alphabet.segments],
+        0,
+        &masks,
+        &alphabet.masks(),
+        &twins,
+        &mut |wiring| {
+            solutions.push(wiring);
+            true
+        },
+    );
+    if solutions.is_empty() {
+        return Err(DecodeError::NoSolution);
+    }
+
+    // a group of `n` swappable wires can be ordered in n! ways
+    let mut rank = vec![1; alphabet.segments];
+    let mut orders: usize = 1;
+    for (wire, twin) in twins.iter().enumerate() {
+        if let Some(twin) = twin {
+            rank[wire] = rank[*twin] + 1;
+            orders = orders.saturating_mul(rank[wire]);
+        }
+    }
+
+    Ok((solutions, orders))
+}
+
+/// Returns the digit of each signal, which must be the same for every
+/// wiring that fits.
+pub fn decode_signals(signals: &[String]) -> Result<HashMap<String, u64>, DecodeError> {
+    let (wirings, _) = distinct_wirings(signals, &Alphabet::digits())?;
+
+    signals
         .iter()
-        .filter(|x| x.len() == 6)
-        .find(|x| !x.contains(segs[3]))
-        .unwrap();
-    // Digit 6 is the only one with len(6) and missing segment c
-    digits[6] = signals
+        .map(|x| {
+            let mut digits: Vec<u64> = wirings.iter().map(|w| w.digit_for(x).unwrap()).collect();
+            digits.sort_unstable();
+            digits.dedup();
+            match digits.len() {
+                1 => Ok((x.chars().sorted().collect(), digits[0])),
+                _ => Err(DecodeError::Ambiguous(
+                    digits.iter().map(|x| x.to_string()).collect(),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Decodes the output of a display into its glyphs. The output patterns
+/// are taken into account to find the wiring too, so they might settle
+/// what the signals leave ambiguous.
+pub fn decode_output_for(
+    display: &SegmentsDisplay,
+    alphabet: &Alphabet,
+) -> Result<String, DecodeError> {
+    let (signals, output) = display;
+    let patterns: Vec<String> = signals.iter().chain(output.iter()).cloned().collect();
+    let (wirings, _) = distinct_wirings(&patterns, alphabet)?;
+
+    let mut values: Vec<String> = wirings
         .iter()
-        .filter(|x| x.len() == 6)
-        .find(|x| !x.contains(segs[2]))
-        .unwrap();
+        .map(|wiring| {
+            output
+                .iter()
+                .map(|x| wiring.glyph_for(x, alphabet).unwrap())
+                .collect()
+        })
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+
+    match values.len() {
+        1 => Ok(values.remove(0)),
+        _ => Err(DecodeError::Ambiguous(values)),
+    }
+}
+
+pub fn decode_output(display: &SegmentsDisplay) -> Result<u64, DecodeError> {
+    let digits = decode_output_for(display, &Alphabet::digits())?;
+    if digits.is_empty() {
+        return Err(DecodeError::EmptyOutput);
+    }
 
     digits
+        .chars()
+        .try_fold(0u64, |value, x| {
+            value
+                .checked_mul(10)?
+                .checked_add(x.to_digit(10).unwrap() as u64)
+        })
+        .ok_or(DecodeError::Overflow(digits))
+}
+
+/// Draws seven-segment glyphs side by side, like the puzzle does. Each
+/// glyph has the character to draw on every segment, from `a` to `g`.
+pub fn draw_glyphs(glyphs: &[[char; 7]]) -> String {
+    let horizontal = |x: char| format!(" {} ", x.to_string().repeat(4));
+    let vertical = |l: char, r: char| format!("{}    {}", l, r);
+
+    let rows: Vec<Vec<String>> = glyphs
         .iter()
-        .enumerate()
-        .map(|(index, digit)| (digit.chars().sorted().collect(), index as u64))
-        .collect()
+        .map(|g| {
+            vec![
+                horizontal(g[0]),
+                vertical(g[1], g[2]),
+                vertical(g[1], g[2]),
+                horizontal(g[3]),
+                vertical(g[4], g[5]),
+                vertical(g[4], g[5]),
+                horizontal(g[6]),
+            ]
+        })
+        .collect();
+
+    let mut buffer = String::new();
+    for row in 0..7 {
+        let line: Vec<&str> = rows.iter().map(|x| x[row].as_str()).collect();
+        buffer.push_str(line.join("  ").trim_end());
+        buffer.push('\n');
+    }
+    buffer
 }
 
-fn output_value(display: &SegmentsDisplay) -> u64 {
+/// Returns what to draw on each segment: `label` for the lit ones and `.`
+/// for the rest.
+fn glyph_segments<F>(segments: u32, label: F) -> [char; 7]
+where
+    F: Fn(usize) -> char,
+{
+    let mut glyph = ['.'; 7];
+    for (i, x) in glyph.iter_mut().enumerate() {
+        if segments & (1 << i) != 0 {
+            *x = label(i);
+        }
+    }
+    glyph
+}
+
+fn display_wirings(display: &SegmentsDisplay) -> Result<(Vec<Wiring>, usize), DecodeError> {
     let (signals, output) = display;
-    let mapping = decode_signals(signals);
+    let patterns: Vec<String> = signals.iter().chain(output.iter()).cloned().collect();
+    distinct_wirings(&patterns, &Alphabet::digits())
+}
+
+/// Draws the decoded output digits, with their segments in place.
+pub fn render_output(display: &SegmentsDisplay) -> Result<String, DecodeError> {
+    // fail if the output is ambiguous; otherwise every wiring lights the
+    // same segments for it, so any of them will do
+    decode_output(display)?;
+    let wiring = display_wirings(display)?.0.remove(0);
+    let (_, output) = display;
 
-    output
+    let glyphs: Vec<[char; 7]> = output
         .iter()
-        .map(|chunk| chunk.chars().sorted().collect::<String>())
-        .map(|chunk| mapping[&chunk])
-        .join("")
-        .parse::<u64>()
-        .unwrap()
+        .map(|x| {
+            let segments = wiring.translate(pattern_mask(x, 7).unwrap());
+            glyph_segments(segments, |i| (b'a' + i as u8) as char)
+        })
+        .collect();
+    Ok(draw_glyphs(&glyphs))
+}
+
+/// Draws every scrambled pattern of a display (signals first, then the
+/// output) with the wire that lights each segment, followed by the digit
+/// it was decoded as. It shows at a glance where a wiring went wrong.
+/// The wiring must be the only one that fits.
+pub fn render_wiring(display: &SegmentsDisplay) -> Result<String, DecodeError> {
+    let (mut wirings, orders) = display_wirings(display)?;
+    let count = wirings.len().saturating_mul(orders);
+    if count > 1 {
+        return Err(DecodeError::AmbiguousWiring(count));
+    }
+    let wiring = wirings.remove(0);
+    let (signals, output) = display;
+
+    let mut wire_for = ['?'; 7];
+    for wire in 0..7 {
+        wire_for[wiring.segments[wire]] = (b'a' + wire as u8) as char;
+    }
+
+    let mut buffer = format!("Wiring: {}\n", wiring);
+    for (title, patterns) in [("Signals", signals), ("Output", output)].iter() {
+        let glyphs: Vec<[char; 7]> = patterns
+            .iter()
+            .map(|x| {
+                let segments = wiring.translate(pattern_mask(x, 7).unwrap());
+                glyph_segments(segments, |i| wire_for[i])
+            })
+            .collect();
+        let captions: Vec<String> = patterns
+            .iter()
+            .map(|x| format!("{:^6}", wiring.digit_for(x).unwrap()))
+            .collect();
+
+        buffer.push_str(&format!("{}:\n", title));
+        buffer.push_str(&draw_glyphs(&glyphs));
+        buffer.push_str(captions.join("  ").trim_end());
+        buffer.push('\n');
+    }
+    Ok(buffer)
+}
+
+fn output_value(display: &SegmentsDisplay) -> u64 {
+    decode_output(display).unwrap_or_else(|err| panic!("{}", err))
 }
 
 #[aoc(day8, part2)]
@@ -193,7 +625,7 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
         let (signals, _) = display.first().unwrap();
 
         assert_eq!(
-            decode_signals(signals),
+            decode_signals(signals).unwrap(),
             HashMap::from([
                 ("abcdeg".to_string(), 0),
                 ("ab".to_string(), 1),
@@ -222,4 +654,275 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
         let input = parse_input(INPUT);
         assert_eq!(solve_part2(&input), 61229);
     }
+
+    #[test]
+    fn test_day8_solve_wiring() {
+        let display = parse_input(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        );
+        let (signals, _) = display.first().unwrap();
+        let wirings = solve_wiring(signals).unwrap();
+
+        assert_eq!(wirings.len(), 1);
+        assert_eq!(wirings[0].to_string(), "a->c b->f c->g d->a e->b f->d g->e");
+        assert_eq!(wirings[0].segment_for('d'), Some('a'));
+        assert_eq!(wirings[0].segment_for('z'), None);
+    }
+
+    #[test]
+    fn test_day8_partial_signals() {
+        // 1 and 7 are missing, but the other digits are enough
+        let display =
+            parse_input("cdfbe gcdfa fbcad cefabd cdfgeb eafb cagedb | cdfeb fcadb cdfeb cdbaf");
+        assert_eq!(decode_output(&display[0]), Ok(5353));
+
+        let display = parse_input("ab | ab ab");
+        assert_eq!(decode_output(&display[0]), Ok(11));
+        let display = parse_input("ab abe | abcd");
+        assert_eq!(decode_output(&display[0]), Ok(4));
+
+        // any digit with five segments fits
+        let display = parse_input("acedgfb | cdfeb");
+        assert_eq!(
+            decode_output(&display[0]),
+            Err(DecodeError::Ambiguous(vec![
+                "2".to_string(),
+                "3".to_string(),
+                "5".to_string()
+            ]))
+        );
+
+        assert_eq!(
+            decode_signals(&["abcdefg".to_string(), "abcde".to_string()]),
+            Err(DecodeError::Ambiguous(vec![
+                "2".to_string(),
+                "3".to_string(),
+                "5".to_string()
+            ]))
+        );
+
+        let display = parse_input("ab cd | ab");
+        assert_eq!(decode_output(&display[0]), Err(DecodeError::NoSolution));
+        let display = parse_input("abz | ab");
+        assert_eq!(
+            decode_output(&display[0]),
+            Err(DecodeError::InvalidPattern("abz".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_day8_alphabet() {
+        assert!(Alphabet::new(7, &[('1', "cf"), ('7', "acf")]).is_ok());
+        assert_eq!(
+            Alphabet::new(7, &[('1', "cf"), ('l', "fc")]),
+            Err(DecodeError::InvalidAlphabet(
+                "1 and l look the same".to_string()
+            ))
+        );
+        assert!(Alphabet::new(3, &[('1', "ad")]).is_err());
+        assert!(Alphabet::new(0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_day8_hex_alphabet() {
+        // every glyph, wired like in the puzzle example
+        let wiring = "a->c b->f c->g d->a e->b f->d g->e";
+        let scramble = |segments: &str| -> String {
+            segments
+                .chars()
+                .map(|x| {
+                    let i = wiring.find(&format!("->{}", x)).unwrap();
+                    wiring[i - 1..].chars().next().unwrap()
+                })
+                .collect()
+        };
+        let hex = Alphabet::hex();
+        let signals: Vec<String> = hex
+            .glyphs
+            .iter()
+            .map(|(_, mask)| {
+                let segments: String = (0..7)
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| (b'a' + i as u8) as char)
+                    .collect();
+                scramble(&segments)
+            })
+            .collect();
+        let output = vec![scramble("abcdef"), scramble("abdeg"), scramble("cf")];
+
+        let wirings = solve_wiring_for(&signals, &hex).unwrap();
+        assert_eq!(wirings.len(), 1);
+        assert_eq!(wirings[0].to_string(), wiring);
+        assert_eq!(
+            decode_output_for(&(signals, output), &hex),
+            Ok("AE1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_day8_custom_alphabet() {
+        // a few letters on a 14-segment display: a-f are the outer segments
+        // (clockwise from the top), g/h the middle ones, i-k the upper
+        // diagonals and vertical, l-n the lower ones
+        let letters = Alphabet::new(
+            14,
+            &[
+                ('A', "abcefgh"),
+                ('H', "bcefgh"),
+                ('I', "adjm"),
+                ('K', "efgkn"),
+                ('X', "ikln"),
+                ('Z', "adkl"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(letters.segments(), 14);
+
+        // wire `a` goes to segment `n`, `b` to `m` and so on
+        let scramble = |segments: &str| -> String {
+            segments
+                .chars()
+                .map(|x| (b'a' + b'n' - x as u8) as char)
+                .collect()
+        };
+        let signals: Vec<String> = ["abcefgh", "bcefgh", "adjm", "efgkn", "ikln", "adkl"]
+            .iter()
+            .map(|x| scramble(x))
+            .collect();
+        let display = (signals, vec![scramble("bcefgh"), scramble("ikln")]);
+
+        let wirings = solve_wiring_for(&display.0, &letters).unwrap();
+        assert!(wirings
+            .iter()
+            .any(|x| x.segment_for('a') == Some('n') && x.segment_for('n') == Some('a')));
+        assert_eq!(decode_output_for(&display, &letters), Ok("HX".to_string()));
+    }
+
+    #[test]
+    fn test_day8_partial_custom_alphabet() {
+        // same 14-segment letters, but only a pattern or two to go on: there
+        // are hundreds of millions of wirings, too many to go through
+        let letters = Alphabet::new(
+            14,
+            &[
+                ('A', "abcefgh"),
+                ('H', "bcefgh"),
+                ('I', "adjm"),
+                ('K', "efgkn"),
+                ('X', "ikln"),
+                ('Z', "adkl"),
+            ],
+        )
+        .unwrap();
+
+        let display = (vec![], vec!["abcd".to_string()]);
+        assert_eq!(
+            decode_output_for(&display, &letters),
+            Err(DecodeError::Ambiguous(vec![
+                "I".to_string(),
+                "X".to_string(),
+                "Z".to_string()
+            ]))
+        );
+
+        // only `K` has five segments, and `Z` is the only 4-segment letter
+        // that shares a single one with it
+        let display = (vec!["abcde".to_string()], vec!["efgh".to_string()]);
+        assert_eq!(decode_output_for(&display, &letters), Ok("Z".to_string()));
+    }
+
+    #[test]
+    fn test_day8_render_output() {
+        let display = parse_input(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        );
+        let expected = concat!(
+            " aaaa    aaaa    aaaa    aaaa\n",
+            "b    .  .    c  b    .  .    c\n",
+            "b    .  .    c  b    .  .    c\n",
+            " dddd    dddd    dddd    dddd\n",
+            ".    f  .    f  .    f  .    f\n",
+            ".    f  .    f  .    f  .    f\n",
+            " gggg    gggg    gggg    gggg\n",
+        );
+
+        assert_eq!(render_output(&display[0]), Ok(expected.to_string()));
+        assert_eq!(
+            render_output(&parse_input("acedgfb | cdfeb")[0]),
+            Err(DecodeError::Ambiguous(vec![
+                "2".to_string(),
+                "3".to_string(),
+                "5".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_day8_render_wiring() {
+        let display = parse_input(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        );
+        let rendered = render_wiring(&display[0]).unwrap();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "Wiring: a->c b->f c->g d->a e->b f->d g->e");
+        assert_eq!(lines[1], "Signals:");
+        // the 8 is drawn like the wiring in the puzzle description
+        assert!(lines[2].starts_with(" dddd "));
+        assert!(lines[3].starts_with("e    a "));
+        assert!(lines[5].starts_with(" ffff "));
+        assert!(lines[6].starts_with("g    b "));
+        assert!(lines[8].starts_with(" cccc "));
+        assert_eq!(
+            lines[9],
+            "  8       5       2       3       7       9       6       4       0       1"
+        );
+        assert_eq!(lines[10], "Output:");
+        assert_eq!(lines[18], "  5       3       5       3");
+    }
+
+    #[test]
+    fn test_day8_render_ambiguous_wiring() {
+        let display = parse_input("ab abd | ab");
+
+        assert_eq!(decode_output(&display[0]), Ok(1));
+        assert!(render_output(&display[0]).is_ok());
+        let patterns = ["ab".to_string(), "abd".to_string()];
+        assert_eq!(
+            render_wiring(&display[0]),
+            Err(DecodeError::AmbiguousWiring(
+                solve_wiring(&patterns).unwrap().len()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_day8_wiring_search_stops() {
+        // 2! * 10! wirings fit, but they all decode the same way
+        let alphabet = Alphabet::new(12, &[('1', "ab"), ('7', "abc"), ('L', "jkl")]).unwrap();
+        let display = (vec!["kl".to_string()], vec!["kl".to_string()]);
+        assert_eq!(decode_output_for(&display, &alphabet), Ok("1".to_string()));
+
+        let mut visited = 0;
+        for_each_wiring_for(&display.0, &alphabet, |_| {
+            visited += 1;
+            visited < 3
+        })
+        .unwrap();
+        assert_eq!(visited, 3);
+    }
+
+    #[test]
+    fn test_day8_decode_output_bounds() {
+        let display = parse_input("ab abe | ");
+        assert_eq!(decode_output(&display[0]), Err(DecodeError::EmptyOutput));
+
+        let display = (vec!["ab".to_string()], vec!["abcdefg".to_string(); 20]);
+        assert_eq!(
+            decode_output(&display),
+            Err(DecodeError::Overflow("8".repeat(20)))
+        );
+        let display = (vec!["ab".to_string()], vec!["ab".to_string(); 19]);
+        assert_eq!(decode_output(&display), Ok(1_111_111_111_111_111_111));
+    }
 }
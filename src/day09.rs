@@ -64,11 +64,10 @@ fn basin_at(map: &Grid<u64>, coords: (usize, usize), marked: &mut Grid<bool>) ->
         .neighbors_at(x, y)
         .into_iter()
         .filter(|(n, _)| *n > height)
-        .map(|(_, point)| basin_at(map, point, marked))
-        .flatten()
+        .flat_map(|(_, point)| basin_at(map, point, marked))
         .collect();
 
-    vec![vec![(height, coords)], neighbors].concat()
+    [vec![(height, coords)], neighbors].concat()
 }
 
 #[aoc(day9, part2)]
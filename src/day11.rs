@@ -1,10 +1,11 @@
 use aoc_runner_derive::aoc;
 
+use crate::simulation::{Frame, Simulation};
 use crate::utils::Grid;
 
 type Octopus = u64;
 
-fn parse_input(input: &str) -> Grid<Octopus> {
+pub fn parse_input(input: &str) -> Grid<Octopus> {
     let octopuses: Vec<Vec<Octopus>> = input
         .lines()
         .map(|row| {
@@ -13,13 +14,13 @@ fn parse_input(input: &str) -> Grid<Octopus> {
                 .collect()
         })
         .collect();
-    let width = octopuses.get(0).unwrap().len();
+    let width = octopuses.first().unwrap().len();
     let cells: Vec<Octopus> = octopuses.into_iter().flatten().collect();
     Grid::new(&cells, width)
 }
 
 #[derive(Debug, Clone)]
-struct OctopusSim {
+pub struct OctopusSim {
     pub map: Grid<Octopus>,
 }
 
@@ -94,6 +95,31 @@ impl OctopusSim {
     }
 }
 
+impl Simulation for OctopusSim {
+    fn step(&mut self) -> bool {
+        self.tick();
+        true
+    }
+
+    fn snapshot(&self) -> Frame {
+        let (width, height) = self.map.size();
+        let flashes = self.map.cells.iter().filter(|x| **x == 0).count();
+
+        Frame {
+            width,
+            height,
+            // octopuses that just flashed are the brightest
+            cells: self
+                .map
+                .cells
+                .iter()
+                .map(|x| if *x == 0 { 255 } else { *x as u8 * 20 })
+                .collect(),
+            caption: format!("{} flashes", flashes),
+        }
+    }
+}
+
 #[aoc(day11, part1)]
 pub fn solve_part1(input: &str) -> u64 {
     let map = parse_input(input);
@@ -145,4 +171,15 @@ mod tests {
     fn test_day11_solve_part2() {
         assert_eq!(solve_part2(RAW_INPUT), 195);
     }
+
+    #[test]
+    fn test_day11_snapshot() {
+        let mut sim = OctopusSim::new(parse_input("11111\n19991\n19191\n19991\n11111"));
+        sim.step();
+        let frame = sim.snapshot();
+
+        assert_eq!(frame.caption, "9 flashes");
+        assert_eq!(frame.cell_at(0, 0), 3 * 20);
+        assert_eq!(frame.cell_at(1, 1), 255);
+    }
 }
@@ -3,6 +3,8 @@ use aoc_runner_derive::aoc_generator;
 
 use std::collections::HashMap;
 
+use crate::trace::{self, EventKind};
+
 type Edge = (String, String);
 
 #[derive(Debug, PartialEq, Clone)]
@@ -37,6 +39,8 @@ impl CaveSystem {
     }
 
     pub fn search_paths_v1(&self, start: &str, end: &str, partial: &[String]) -> Vec<Vec<String>> {
+        trace::emit(12, EventKind::NodeExpanded);
+
         // add current node into the partial route
         let new_partial: Vec<String> = vec![partial.to_owned(), vec![start.to_owned()]]
             .into_iter()
@@ -53,8 +57,7 @@ impl CaveSystem {
         });
 
         candidates
-            .map(|cave| self.search_paths_v1(cave, end, &new_partial))
-            .flatten()
+            .flat_map(|cave| self.search_paths_v1(cave, end, &new_partial))
             .collect()
     }
 
@@ -65,6 +68,8 @@ impl CaveSystem {
         partial: &[String],
         visited: &str,
     ) -> Vec<Vec<String>> {
+        trace::emit(12, EventKind::NodeExpanded);
+
         // add current node into the partial route
         let new_partial: Vec<String> = vec![partial.to_owned(), vec![start.to_owned()]]
             .into_iter()
@@ -94,7 +99,7 @@ impl CaveSystem {
         });
 
         let mut paths: Vec<Vec<String>> = candidates
-            .map(|cave| {
+            .flat_map(|cave| {
                 if visited.is_empty() && Self::cave_size_for(cave) == CaveSize::Small {
                     [
                         self.search_paths_v2(cave, end, &new_partial, cave),
@@ -105,7 +110,6 @@ impl CaveSystem {
                     [self.search_paths_v2(cave, end, &new_partial, visited)].concat()
                 }
             })
-            .flatten()
             .collect();
 
         // remove duplicates
@@ -177,4 +181,15 @@ mod tests {
         assert_eq!(solve_part2(&parse_input(INPUT[1])), 103);
         assert_eq!(solve_part2(&parse_input(INPUT[2])), 3509);
     }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_day12_trace_nodes_expanded() {
+        use crate::trace::{with_subscriber, Counter};
+        use std::sync::Arc;
+
+        let counter = Arc::new(Counter::default());
+        with_subscriber(counter.clone(), || solve_part1(&parse_input(INPUT[0])));
+        assert_eq!(counter.count(12, EventKind::NodeExpanded), 27);
+    }
 }
@@ -4,6 +4,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use std::fmt;
 
+use crate::simulation::{Frame, Simulation};
 use crate::utils::Grid;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -73,6 +74,48 @@ impl fmt::Display for Manual {
     }
 }
 
+/// Applies the instructions of a manual one fold at a time.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Folding {
+    manual: Manual,
+    done: usize,
+}
+
+impl Folding {
+    pub fn new(manual: Manual) -> Self {
+        Self { manual, done: 0 }
+    }
+}
+
+impl Simulation for Folding {
+    fn step(&mut self) -> bool {
+        match self.manual.instructions.get(self.done).copied() {
+            Some(fold) => {
+                self.manual.fold(fold);
+                self.done += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn snapshot(&self) -> Frame {
+        let (width, height) = self.manual.grid.size();
+        Frame {
+            width,
+            height,
+            cells: self
+                .manual
+                .grid
+                .cells
+                .iter()
+                .map(|x| if *x { 255 } else { 0 })
+                .collect(),
+            caption: format!("{}/{} folds", self.done, self.manual.instructions.len()),
+        }
+    }
+}
+
 #[aoc_generator(day13)]
 pub fn parse_input(input: &str) -> Manual {
     lazy_static! {
@@ -131,7 +174,7 @@ pub fn parse_input(input: &str) -> Manual {
 #[aoc(day13, part1)]
 pub fn solve_part1(manual: &Manual) -> u64 {
     let mut manual = manual.to_owned();
-    let fold = *manual.instructions.get(0).unwrap();
+    let fold = *manual.instructions.first().unwrap();
 
     manual.fold(fold);
 
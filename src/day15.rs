@@ -1,3 +1,4 @@
+use crate::trace::{self, EventKind};
 use crate::utils::Grid;
 use aoc_runner_derive::aoc;
 
@@ -54,6 +55,7 @@ fn a_star(grid: &Grid<usize>, start: Point, end: Point) -> Vec<Tile> {
 
     while !frontier.is_empty() {
         let current = frontier.pop().unwrap();
+        trace::emit(15, EventKind::NodeExpanded);
 
         if current == goal {
             break;
@@ -63,7 +65,7 @@ fn a_star(grid: &Grid<usize>, start: Point, end: Point) -> Vec<Tile> {
             grid.neighbors_at(current.position.0 as i32, current.position.1 as i32)
         {
             let new_cost = cost_so_far[&current.position] + neighbor_cost;
-            if cost_so_far.get(&neighbor_position).is_none()
+            if !cost_so_far.contains_key(&neighbor_position)
                 || new_cost < cost_so_far[&neighbor_position]
             {
                 cost_so_far.insert(neighbor_position, new_cost);
@@ -94,7 +96,7 @@ fn parse_input(input: &str) -> Grid<usize> {
         })
         .collect();
 
-    let width = tiles.get(0).unwrap().len();
+    let width = tiles.first().unwrap().len();
     let cells: Vec<usize> = tiles.into_iter().flatten().collect();
 
     Grid::<usize>::new(&cells, width)
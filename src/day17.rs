@@ -5,6 +5,8 @@ use regex::Regex;
 
 use std::cmp::Ordering;
 
+use crate::trace::{self, EventKind};
+
 type Point = (i64, i64);
 type Area = (Point, Point);
 
@@ -53,6 +55,8 @@ fn is_inside_bbox(point: Point, area: Area) -> bool {
 }
 
 fn shoot(velocity: Point, area: Area) -> Option<i64> {
+    trace::emit(17, EventKind::Iteration);
+
     let mut probe = Probe {
         velocity,
         position: (0, 0),
@@ -99,7 +103,7 @@ pub fn solve_part2(area: &Area) -> usize {
         }
     }
 
-    shots.len() as usize
+    shots.len()
 }
 
 #[cfg(test)]
@@ -125,4 +129,17 @@ mod tests {
         let area = ((20, 30), (-10, -5));
         assert_eq!(solve_part2(&area), 112);
     }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_day17_trace_iterations() {
+        use crate::trace::{with_subscriber, Counter};
+        use std::sync::Arc;
+
+        let area = ((20, 30), (-10, -5));
+        let counter = Arc::new(Counter::default());
+        with_subscriber(counter.clone(), || solve_part1(&area));
+        // one shot per velocity in the 31 x 510 scan
+        assert_eq!(counter.count(17, EventKind::Iteration), 31 * 510);
+    }
 }
@@ -20,4 +20,10 @@ pub mod day15;
 pub mod day16;
 pub mod day17;
 
+pub mod answers;
+pub mod player;
+pub mod runner;
+pub mod simulation;
+pub mod trace;
+
 aoc_lib! { year = 2021 }
@@ -0,0 +1,523 @@
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io::{self, BufReader};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use aoc_2021::answers::{self, AnswerStore};
+use aoc_2021::day02::Submarine;
+use aoc_2021::runner::Outcome;
+use aoc_2021::{day01, day02, day05, day06, day08, player, runner, simulation};
+
+const USAGE: &str = "Usage:
+    aoc-2021 [DAY...] [--timeout SECONDS] [--progress] [--answers FILE]
+    aoc-2021 visualize DAY [--steps N] [--delay MS] [--frames DIR]
+    aoc-2021 play DAY [--steps N] [--delay MS]
+    aoc-2021 record DAY PART ANSWER right|too-high|too-low [--answers FILE]
+    aoc-2021 sonar [--window N] [--sigmas K] [--json]
+    aoc-2021 depths [FILE|-] [--window N]
+    aoc-2021 trajectory [--model NAME] [--format csv|svg]
+    aoc-2021 models
+    aoc-2021 vents [--threshold N] [--map] [--image FILE]
+    aoc-2021 lanternfish [--days N]
+    aoc-2021 displays [LINE...] [--wiring]";
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+#[derive(Debug)]
+struct Options {
+    days: Vec<u8>,
+    timeout: Duration,
+    progress: bool,
+    answers: PathBuf,
+}
+
+type Mode = fn(&[String]) -> Result<(), String>;
+
+#[derive(Debug)]
+struct VisualizeOptions {
+    day: u8,
+    steps: usize,
+    delay: Duration,
+    frames_dir: Option<PathBuf>,
+}
+
+fn input_path(day: u8) -> String {
+    // same location cargo-aoc downloads the inputs to
+    format!("input/2021/day{}.txt", day)
+}
+
+fn read_input(day: u8) -> Result<String, String> {
+    let path = input_path(day);
+    fs::read_to_string(&path)
+        .map(|x| x.trim_end_matches('\n').to_string())
+        .map_err(|err| format!("could not read {}: {}", path, err))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        days: vec![],
+        timeout: runner::DEFAULT_TIMEOUT,
+        progress: false,
+        answers: PathBuf::from(answers::DEFAULT_PATH),
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--timeout" => {
+                options.timeout = args
+                    .next()
+                    .and_then(|x| x.parse::<f64>().ok())
+                    .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                    .ok_or("--timeout expects a number of seconds")?;
+            }
+            "--progress" => options.progress = true,
+            "--answers" => {
+                options.answers = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or("--answers expects a file")?;
+            }
+            day => options.days.push(
+                day.parse::<u8>()
+                    .map_err(|_| format!("invalid day: {}", day))?,
+            ),
+        }
+    }
+
+    if options.days.is_empty() {
+        options.days = (1..=25).collect();
+    }
+
+    Ok(options)
+}
+
+fn parse_visualize_args(args: &[String]) -> Result<VisualizeOptions, String> {
+    let mut args = args.iter();
+    let day = args
+        .next()
+        .and_then(|x| x.parse::<u8>().ok())
+        .ok_or("visualize expects a day")?;
+    let mut options = VisualizeOptions {
+        day,
+        steps: 100,
+        delay: Duration::from_millis(200),
+        frames_dir: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {}", arg))?;
+        match arg.as_str() {
+            "--steps" => {
+                options.steps = value
+                    .parse::<usize>()
+                    .map_err(|_| "--steps expects a number")?;
+            }
+            "--delay" => {
+                let millis = value
+                    .parse::<u64>()
+                    .map_err(|_| "--delay expects milliseconds")?;
+                options.delay = Duration::from_millis(millis);
+            }
+            "--frames" => options.frames_dir = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_args(args)?;
+    if options.progress {
+        progress::enable()?;
+    }
+    let store = load_answers(&options.answers)?;
+
+    // panics are reported along with the answers, so keep stderr quiet
+    panic::set_hook(Box::new(|_| {}));
+
+    for report in runner::run_days(&options.days, read_input, options.timeout) {
+        println!("{}", report);
+        if let Outcome::Answer(answer) = &report.outcome {
+            for warning in store.check(report.day, report.part, answer) {
+                println!("    warning: {}", warning);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn load_answers(path: &Path) -> Result<AnswerStore, String> {
+    AnswerStore::load(path).map_err(|err| format!("could not load {}: {}", path.display(), err))
+}
+
+fn record(args: &[String]) -> Result<(), String> {
+    let mut path = PathBuf::from(answers::DEFAULT_PATH);
+    let mut values = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--answers" => {
+                path = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or("--answers expects a file")?
+            }
+            value => values.push(value),
+        }
+    }
+
+    if values.len() != 4 {
+        return Err("record expects a day, a part, an answer and a status".to_string());
+    }
+    let day = values[0]
+        .parse::<u8>()
+        .map_err(|_| format!("invalid day: {}", values[0]))?;
+    let part = values[1]
+        .parse::<u8>()
+        .map_err(|_| format!("invalid part: {}", values[1]))?;
+    let status = answers::Status::try_from(values[3])?;
+
+    let mut store = load_answers(&path)?;
+    store.record(day, part, values[2], status)?;
+    store
+        .save(&path)
+        .map_err(|err| format!("could not save {}: {}", path.display(), err))
+}
+
+fn sonar(args: &[String]) -> Result<(), String> {
+    let mut window_size = 3;
+    let mut sigmas = 3.0;
+    let mut json = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--window" => {
+                window_size = args
+                    .next()
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .ok_or("--window expects a number")?;
+            }
+            "--sigmas" => {
+                sigmas = args
+                    .next()
+                    .and_then(|x| x.parse::<f64>().ok())
+                    .ok_or("--sigmas expects a number")?;
+            }
+            "--json" => json = true,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    let measurements = day01::parse_input(&read_input(1)?);
+    let report = day01::SonarReport::new(&measurements, window_size, sigmas)
+        .ok_or("no measurements to report on")?;
+
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+    Ok(())
+}
+
+fn depths(args: &[String]) -> Result<(), String> {
+    let mut window_size = 3;
+    let mut path = input_path(1);
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--window" => {
+                window_size = args
+                    .next()
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .ok_or("--window expects a number")?;
+            }
+            file => path = file.to_string(),
+        }
+    }
+
+    // the log is read line by line, so it can be larger than the memory
+    let analysis = if path == "-" {
+        day01::analyse_reader(io::stdin().lock(), window_size)?
+    } else {
+        let file =
+            fs::File::open(&path).map_err(|err| format!("could not read {}: {}", path, err))?;
+        day01::analyse_reader(BufReader::new(file), window_size)?
+    };
+    print!("{}", analysis);
+    Ok(())
+}
+
+fn trajectory(args: &[String]) -> Result<(), String> {
+    let mut model = "v2".to_string();
+    let mut format = "csv".to_string();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {}", arg))?;
+        match arg.as_str() {
+            "--model" => model = value.to_owned(),
+            "--format" => format = value.to_owned(),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    let program = day02::parse_program(&read_input(2)?).map_err(|err| err.to_string())?;
+    let mut submarine = day02::model(&model)
+        .ok_or(format!("unknown model: {}", model))?
+        .with_history();
+    submarine.run_program(&program);
+    let history = submarine.history().unwrap();
+
+    match format.as_str() {
+        "csv" => print!("{}", history.to_csv()),
+        "svg" => print!("{}", history.to_svg()),
+        _ => return Err(format!("unknown format: {}", format)),
+    }
+    eprintln!(
+        "max depth: {}, total distance: {:.2}",
+        history.max_depth().unwrap(),
+        history.total_distance()
+    );
+    Ok(())
+}
+
+fn models(args: &[String]) -> Result<(), String> {
+    if let Some(arg) = args.first() {
+        return Err(format!("unknown option: {}", arg));
+    }
+
+    let program = day02::parse_program(&read_input(2)?).map_err(|err| err.to_string())?;
+    println!(
+        "{:<14}{:>10}{:>10}{:>10}{:>16}",
+        "model", "x", "depth", "aim", "x * depth"
+    );
+    for (name, physics) in day02::MODELS {
+        let mut submarine = day02::ConfigurableSubmarine::new(*physics);
+        submarine.run_program(&program);
+        let state = submarine.state();
+        println!(
+            "{:<14}{:>10}{:>10}{:>10}{:>16}",
+            name,
+            state.x,
+            state.y,
+            state.aim,
+            state.x * state.y
+        );
+    }
+    Ok(())
+}
+
+fn vents(args: &[String]) -> Result<(), String> {
+    let mut threshold = 2;
+    let mut show_map = false;
+    let mut image = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threshold" => {
+                threshold = args
+                    .next()
+                    .and_then(|x| x.parse::<u64>().ok())
+                    .ok_or("--threshold expects a number")?;
+            }
+            "--map" => show_map = true,
+            "--image" => image = Some(PathBuf::from(args.next().ok_or("--image expects a path")?)),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    let map = day05::CoverageMap::new(&day05::parse_input(&read_input(5)?));
+
+    println!(
+        "Cells covered by at least {} vents: {}",
+        threshold,
+        map.at_least(threshold)
+    );
+    if let Some((max, cells)) = map.hottest() {
+        println!("Hottest cells ({} vents): {:?}", max, cells);
+    }
+    println!("Overlap histogram:");
+    for (count, cells) in map.histogram() {
+        println!("  {:>3}: {}", count, cells);
+    }
+    if show_map {
+        print!("{}", map);
+    }
+    if let Some(path) = image {
+        fs::write(&path, map.to_frame().to_pgm())
+            .map_err(|err| format!("could not write {}: {}", path.display(), err))?;
+    }
+    Ok(())
+}
+
+fn lanternfish(args: &[String]) -> Result<(), String> {
+    let mut days = 256;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--days" => {
+                days = args
+                    .next()
+                    .and_then(|x| x.parse::<u64>().ok())
+                    .ok_or("--days expects a number")?;
+            }
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    let fishes = day06::parse_input(&read_input(6)?);
+    let model = day06::PopulationModel::new(&fishes, day06::Lifecycle::default())?;
+    print!("{}", model.to_csv(days));
+    Ok(())
+}
+
+fn displays(args: &[String]) -> Result<(), String> {
+    let mut lines = vec![];
+    let mut show_wiring = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--wiring" => show_wiring = true,
+            line => lines.push(
+                line.parse::<usize>()
+                    .map_err(|_| format!("invalid line: {}", line))?,
+            ),
+        }
+    }
+
+    let input = day08::parse_input(&read_input(8)?);
+    if lines.is_empty() {
+        lines = (1..=input.len()).collect();
+    }
+
+    for line in lines {
+        let display = input
+            .get(line.wrapping_sub(1))
+            .ok_or(format!("no display at line {}", line))?;
+        let rendered = if show_wiring {
+            day08::render_wiring(display)
+        } else {
+            day08::render_output(display)
+        };
+        match rendered {
+            Ok(rendered) => println!("Line {}:\n{}", line, rendered),
+            Err(err) => println!("Line {}: {}\n", line, err),
+        }
+    }
+    Ok(())
+}
+
+fn visualize(args: &[String]) -> Result<(), String> {
+    let options = parse_visualize_args(args)?;
+    let input = read_input(options.day)?;
+    let mut sim = simulation::for_day(options.day, &input)
+        .ok_or(format!("day {} has no simulation", options.day))?;
+
+    if let Some(dir) = &options.frames_dir {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+
+    let mut result = Ok(());
+    simulation::run(sim.as_mut(), options.steps, |step, frame| {
+        match &options.frames_dir {
+            Some(dir) => {
+                let path = dir.join(format!("frame_{:04}.pgm", step));
+                if let Err(err) = fs::write(&path, frame.to_pgm()) {
+                    result = Err(format!("could not write {}: {}", path.display(), err));
+                }
+            }
+            None => {
+                print!("{}Step {} - {}", CLEAR_SCREEN, step, frame);
+                thread::sleep(options.delay);
+            }
+        }
+    });
+
+    result
+}
+
+fn play(args: &[String]) -> Result<(), String> {
+    let options = parse_visualize_args(args)?;
+    if options.frames_dir.is_some() {
+        return Err("--frames can't be used with play".to_string());
+    }
+
+    let input = read_input(options.day)?;
+    let sim = simulation::for_day(options.day, &input)
+        .ok_or(format!("day {} has no simulation", options.day))?;
+
+    player::play(player::Player::new(sim, options.steps, options.delay))
+        .map_err(|err| format!("terminal error: {}", err))
+}
+
+#[cfg(feature = "trace")]
+mod progress {
+    use aoc_2021::trace::{self, Counter, Event, Subscriber};
+    use std::sync::Arc;
+
+    const REPORT_EVERY: u64 = 100_000;
+
+    #[derive(Debug, Default)]
+    struct Progress {
+        counter: Counter,
+    }
+
+    impl Subscriber for Progress {
+        fn on_event(&self, event: &Event) {
+            self.counter.on_event(event);
+            let count = self.counter.count(event.day, event.kind);
+            if count.is_multiple_of(REPORT_EVERY) {
+                eprintln!("[day {}] {:?} x {}", event.day, event.kind, count);
+            }
+        }
+    }
+
+    pub fn enable() -> Result<(), String> {
+        trace::set_global_subscriber(Arc::new(Progress::default()));
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "trace"))]
+mod progress {
+    pub fn enable() -> Result<(), String> {
+        Err("--progress needs a build with the `trace` feature".to_string())
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (mode, args): (Mode, &[String]) = match args.first().map(|x| x.as_str()) {
+        Some("visualize") => (visualize, &args[1..]),
+        Some("play") => (play, &args[1..]),
+        Some("record") => (record, &args[1..]),
+        Some("sonar") => (sonar, &args[1..]),
+        Some("depths") => (depths, &args[1..]),
+        Some("trajectory") => (trajectory, &args[1..]),
+        Some("models") => (models, &args[1..]),
+        Some("vents") => (vents, &args[1..]),
+        Some("lanternfish") => (lanternfish, &args[1..]),
+        Some("displays") => (displays, &args[1..]),
+        _ => (run, &args),
+    };
+
+    if let Err(err) = mode(args) {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::{self, Read, Write};
+use std::process::{Command as Process, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::simulation::{Frame, Simulation};
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_millis(2000);
+
+pub const HELP: &str =
+    "[space] pause  [n/→] next  [p/←] prev  [+/-] speed  [N g] go to step N  [q] quit";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Command {
+    TogglePause,
+    Next,
+    Previous,
+    Faster,
+    Slower,
+    Jump(usize),
+    Quit,
+}
+
+/// Translates raw key presses into player commands. Digits are buffered
+/// in `pending` until `g` or enter turns them into a jump.
+pub fn parse_keys(keys: &[u8], pending: &mut String) -> Vec<Command> {
+    let mut commands = vec![];
+    let mut i = 0;
+
+    while i < keys.len() {
+        // arrow keys are sent as escape sequences: ESC [ C / ESC [ D
+        if keys[i..].starts_with(b"\x1b[C") {
+            commands.push(Command::Next);
+            i += 3;
+            continue;
+        }
+        if keys[i..].starts_with(b"\x1b[D") {
+            commands.push(Command::Previous);
+            i += 3;
+            continue;
+        }
+
+        match keys[i] {
+            b' ' => commands.push(Command::TogglePause),
+            b'n' => commands.push(Command::Next),
+            b'p' => commands.push(Command::Previous),
+            b'+' => commands.push(Command::Faster),
+            b'-' => commands.push(Command::Slower),
+            b'q' => commands.push(Command::Quit),
+            c @ b'0'..=b'9' => pending.push(c as char),
+            b'g' | b'\n' | b'\r' => {
+                if let Ok(step) = pending.parse::<usize>() {
+                    commands.push(Command::Jump(step));
+                }
+                pending.clear();
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    commands
+}
+
+/// Plays a simulation back and forth. Frames are recorded as they get
+/// computed, so going back in time doesn't need to re-run anything.
+pub struct Player {
+    sim: Box<dyn Simulation>,
+    frames: Vec<Frame>,
+    current: usize,
+    max_steps: usize,
+    finished: bool,
+    pub paused: bool,
+    pub delay: Duration,
+}
+
+impl Player {
+    pub fn new(sim: Box<dyn Simulation>, max_steps: usize, delay: Duration) -> Self {
+        let frames = vec![sim.snapshot()];
+        let mut player = Self {
+            sim,
+            frames,
+            current: 0,
+            max_steps,
+            finished: false,
+            paused: false,
+            delay,
+        };
+        player.go_to(0);
+        player
+    }
+
+    pub fn current_step(&self) -> usize {
+        self.current
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frames[self.current]
+    }
+
+    /// Returns whether there are no more steps after the current one.
+    pub fn at_end(&self) -> bool {
+        self.current + 1 == self.frames.len() && self.finished
+    }
+
+    fn record_until(&mut self, step: usize) {
+        while !self.finished && self.frames.len() <= step {
+            if self.frames.len() > self.max_steps || !self.sim.step() {
+                self.finished = true;
+            } else {
+                self.frames.push(self.sim.snapshot());
+            }
+        }
+    }
+
+    pub fn go_to(&mut self, step: usize) {
+        // look one frame ahead, so we know whether this is the last one
+        self.record_until(step.saturating_add(1));
+        self.current = std::cmp::min(step, self.frames.len() - 1);
+    }
+
+    /// Advances one step if the player isn't paused. Returns `false` once
+    /// the simulation is over.
+    pub fn tick(&mut self) -> bool {
+        if !self.paused {
+            self.go_to(self.current + 1);
+        }
+        !self.at_end()
+    }
+
+    /// Applies a command. Returns `false` if the player should quit.
+    pub fn apply(&mut self, command: Command) -> bool {
+        match command {
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Next => {
+                self.paused = true;
+                self.go_to(self.current + 1);
+            }
+            Command::Previous => {
+                self.paused = true;
+                self.go_to(self.current.saturating_sub(1));
+            }
+            Command::Faster => self.delay = std::cmp::max(self.delay / 2, MIN_DELAY),
+            Command::Slower => self.delay = std::cmp::min(self.delay * 2, MAX_DELAY),
+            Command::Jump(step) => {
+                self.paused = true;
+                self.go_to(step);
+            }
+            Command::Quit => return false,
+        }
+        true
+    }
+
+    pub fn render(&self, pending: &str) -> String {
+        let status = if self.paused { "paused" } else { "playing" };
+        let jump = if pending.is_empty() {
+            "".to_string()
+        } else {
+            format!("  go to: {}", pending)
+        };
+
+        format!(
+            "{}Step {} - {}\n{} ({:?}/step){}\n{}\n",
+            CLEAR_SCREEN,
+            self.current,
+            self.frame(),
+            status,
+            self.delay,
+            jump,
+            HELP
+        )
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Process::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Keeps the terminal in non-canonical mode, with the cursor hidden, for
+/// as long as it lives. The original mode is restored when it's dropped,
+/// so it also happens on errors and panics.
+struct RawTerminal {
+    saved_mode: String,
+}
+
+impl RawTerminal {
+    fn enable() -> io::Result<Self> {
+        let terminal = Self {
+            saved_mode: stty(&["-g"])?,
+        };
+        stty(&["-icanon", "-echo", "min", "1"])?;
+        let mut stdout = io::stdout();
+        write!(stdout, "{}", HIDE_CURSOR)?;
+        stdout.flush()?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // nothing else can be done if restoring fails at this point
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "{}", SHOW_CURSOR);
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved_mode]);
+    }
+}
+
+/// Runs the player in the terminal until the user quits. The terminal is
+/// switched to non-canonical mode with `stty`, so keys are read as soon as
+/// they are pressed, and restored afterwards.
+pub fn play(mut player: Player) -> io::Result<()> {
+    let _terminal = RawTerminal::enable()?;
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 16];
+        while let Ok(n) = io::stdin().read(&mut buffer) {
+            if n == 0 || sender.send(buffer[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stdout = io::stdout();
+    let mut pending = String::new();
+
+    loop {
+        write!(stdout, "{}", player.render(&pending))?;
+        stdout.flush()?;
+
+        let timeout = if player.paused || player.at_end() {
+            Duration::from_secs(3600)
+        } else {
+            player.delay
+        };
+        match receiver.recv_timeout(timeout) {
+            Ok(keys) => {
+                let commands = parse_keys(&keys, &mut pending);
+                if !commands.into_iter().all(|x| player.apply(x)) {
+                    break;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                player.tick();
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation;
+
+    const MANUAL: &str = "0,0\n0,4\n4,4\n\nfold along y=2\nfold along x=2";
+
+    fn player() -> Player {
+        let sim = simulation::for_day(13, MANUAL).unwrap();
+        Player::new(sim, 100, Duration::from_millis(100))
+    }
+
+    #[test]
+    fn test_player_parse_keys() {
+        let mut pending = String::new();
+        assert_eq!(
+            parse_keys(b" n\x1b[D+-q", &mut pending),
+            vec![
+                Command::TogglePause,
+                Command::Next,
+                Command::Previous,
+                Command::Faster,
+                Command::Slower,
+                Command::Quit
+            ]
+        );
+        assert_eq!(parse_keys(b"1", &mut pending), vec![]);
+        assert_eq!(parse_keys(b"2g", &mut pending), vec![Command::Jump(12)]);
+        assert_eq!(pending, "");
+    }
+
+    #[test]
+    fn test_player_steps() {
+        let mut player = player();
+
+        assert!(player.tick());
+        assert_eq!(player.current_step(), 1);
+        assert!(!player.tick());
+        assert_eq!(player.current_step(), 2);
+
+        player.apply(Command::Previous);
+        assert!(player.paused);
+        assert_eq!(player.current_step(), 1);
+        assert_eq!(player.frame().caption, "1/2 folds");
+    }
+
+    #[test]
+    fn test_player_jump() {
+        let mut player = player();
+
+        player.apply(Command::Jump(10));
+        assert_eq!(player.current_step(), 2);
+        assert!(player.at_end());
+
+        player.apply(Command::Jump(0));
+        assert_eq!(player.frame().caption, "0/2 folds");
+
+        player.apply(Command::Jump(usize::MAX));
+        assert_eq!(player.current_step(), 2);
+    }
+
+    #[test]
+    fn test_player_speed() {
+        let mut player = player();
+
+        player.apply(Command::Faster);
+        assert_eq!(player.delay, Duration::from_millis(50));
+        player.apply(Command::Slower);
+        player.apply(Command::Slower);
+        assert_eq!(player.delay, Duration::from_millis(200));
+        assert!(!player.apply(Command::Quit));
+    }
+}
@@ -0,0 +1,261 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::*;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Solution {
+    pub day: u8,
+    pub part: u8,
+    /// Returns the answer, or why there is none
+    pub solve: fn(&str) -> Result<String, String>,
+}
+
+macro_rules! solution {
+    ($day:literal, $part:literal, $solver:path) => {
+        Solution {
+            day: $day,
+            part: $part,
+            solve: |input| Ok($solver(input).to_string()),
+        }
+    };
+    ($day:literal, $part:literal, $generator:path, $solver:path) => {
+        Solution {
+            day: $day,
+            part: $part,
+            solve: |input| Ok($solver(&$generator(input)).to_string()),
+        }
+    };
+    // for solvers that return a `Result`
+    ($day:literal, $part:literal, $generator:path, $solver:path, fallible) => {
+        Solution {
+            day: $day,
+            part: $part,
+            solve: |input| {
+                $solver(&$generator(input))
+                    .map(|x| x.to_string())
+                    .map_err(|x| x.to_string())
+            },
+        }
+    };
+}
+
+pub const SOLUTIONS: &[Solution] = &[
+    solution!(1, 1, day01::parse_input, day01::solve_part1),
+    solution!(1, 2, day01::parse_input, day01::solve_part2),
+    solution!(2, 1, day02::parse_input, day02::solve_part1),
+    solution!(2, 2, day02::parse_input, day02::solve_part2),
+    solution!(3, 1, day03::parse_input, day03::solve_part1, fallible),
+    solution!(3, 2, day03::parse_input, day03::solve_part2, fallible),
+    solution!(4, 1, day04::solve_part1),
+    solution!(4, 2, day04::solve_part2),
+    solution!(5, 1, day05::parse_input, day05::solve_part1),
+    solution!(5, 2, day05::parse_input, day05::solve_part2),
+    solution!(6, 1, day06::parse_input, day06::solve_part1),
+    solution!(6, 2, day06::parse_input, day06::solve_part2),
+    solution!(7, 1, day07::parse_input, day07::solve_part1),
+    solution!(7, 2, day07::parse_input, day07::solve_part2),
+    solution!(8, 1, day08::parse_input, day08::solve_part1),
+    solution!(8, 2, day08::parse_input, day08::solve_part2),
+    solution!(9, 1, day09::solve_part1),
+    solution!(9, 2, day09::solve_part2),
+    solution!(10, 1, day10::parse_input, day10::solve_part1),
+    solution!(10, 2, day10::parse_input, day10::solve_part2),
+    solution!(11, 1, day11::solve_part1),
+    solution!(11, 2, day11::solve_part2),
+    solution!(12, 1, day12::parse_input, day12::solve_part1),
+    solution!(12, 2, day12::parse_input, day12::solve_part2),
+    solution!(13, 1, day13::parse_input, day13::solve_part1),
+    solution!(13, 2, day13::parse_input, day13::solve_part2),
+    solution!(14, 1, day14::solve_part1),
+    solution!(15, 1, day15::solve_part1),
+    solution!(15, 2, day15::solve_part2),
+    solution!(16, 1, day16::parse_input, day16::solve_part1),
+    solution!(17, 1, day17::parse_input, day17::solve_part1),
+    solution!(17, 2, day17::parse_input, day17::solve_part2),
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    Answer(String),
+    Failed(String),
+    Panicked(String),
+    TimedOut(Duration),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Answer(answer) => write!(f, "{}", answer),
+            Self::Failed(reason) => write!(f, "ERROR: {}", reason),
+            Self::Panicked(reason) => write!(f, "PANIC: {}", reason),
+            Self::TimedOut(limit) => write!(f, "TIMEOUT after {:?}", limit),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub day: u8,
+    pub part: u8,
+    pub outcome: Outcome,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Day {} - Part {}: {} ({:?})",
+            self.day, self.part, self.outcome, self.elapsed
+        )
+    }
+}
+
+fn panic_reason(payload: Box<dyn Any + Send>) -> String {
+    if let Some(reason) = payload.downcast_ref::<&str>() {
+        reason.to_string()
+    } else if let Some(reason) = payload.downcast_ref::<String>() {
+        reason.to_owned()
+    } else {
+        "unknown reason".to_string()
+    }
+}
+
+/// Runs a single solution in its own thread, so a panic or an endless loop
+/// doesn't take down the rest of the run. A timed out thread can't be
+/// killed, so it is left detached until the process exits.
+pub fn run(solution: &Solution, input: &str, timeout: Duration) -> Report {
+    let (sender, receiver) = mpsc::channel();
+    let solve = solution.solve;
+    let input = input.to_owned();
+    let start = Instant::now();
+
+    let handle = thread::spawn(move || {
+        let answer = solve(&input);
+        // the receiver is gone if we already timed out
+        let _ = sender.send(answer);
+    });
+
+    let outcome = match receiver.recv_timeout(timeout) {
+        Ok(Ok(answer)) => Outcome::Answer(answer),
+        Ok(Err(reason)) => Outcome::Failed(reason),
+        Err(mpsc::RecvTimeoutError::Timeout) => Outcome::TimedOut(timeout),
+        Err(mpsc::RecvTimeoutError::Disconnected) => match handle.join() {
+            Err(payload) => Outcome::Panicked(panic_reason(payload)),
+            Ok(_) => Outcome::Panicked("solver finished without an answer".to_string()),
+        },
+    };
+
+    Report {
+        day: solution.day,
+        part: solution.part,
+        outcome,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Runs every solution for the given days. `input_for` returns the puzzle
+/// input of a day, or an error message if it can't be loaded.
+pub fn run_days<F>(days: &[u8], input_for: F, timeout: Duration) -> Vec<Report>
+where
+    F: Fn(u8) -> Result<String, String>,
+{
+    let mut reports = vec![];
+
+    for &day in days {
+        let solutions = SOLUTIONS.iter().filter(|x| x.day == day);
+        match input_for(day) {
+            Ok(input) => {
+                reports.extend(solutions.map(|solution| run(solution, &input, timeout)));
+            }
+            Err(reason) => {
+                reports.extend(solutions.map(|solution| Report {
+                    day,
+                    part: solution.part,
+                    outcome: Outcome::Failed(reason.to_owned()),
+                    elapsed: Duration::ZERO,
+                }));
+            }
+        }
+    }
+
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_runner_answer() {
+        let solution = solution!(1, 1, day01::parse_input, day01::solve_part1);
+        let report = run(&solution, "199\n200\n208\n210\n200", TIMEOUT);
+
+        assert_eq!(report.outcome, Outcome::Answer("3".to_string()));
+    }
+
+    #[test]
+    fn test_runner_catches_panic() {
+        let solution = solution!(10, 2, day10::parse_input, day10::solve_part2);
+        let report = run(&solution, ")", TIMEOUT);
+
+        assert!(matches!(report.outcome, Outcome::Panicked(_)));
+    }
+
+    #[test]
+    fn test_runner_failure() {
+        let solution = solution!(3, 1, day03::parse_input, day03::solve_part1, fallible);
+        let report = run(&solution, "101\n01", TIMEOUT);
+
+        assert_eq!(
+            report.outcome,
+            Outcome::Failed("Line 2 has 2 bits, expected 3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_runner_timeout() {
+        let solution = Solution {
+            day: 0,
+            part: 1,
+            solve: |_| {
+                thread::sleep(Duration::from_secs(1));
+                Ok("".to_string())
+            },
+        };
+        let report = run(&solution, "", Duration::from_millis(10));
+
+        assert_eq!(report.outcome, Outcome::TimedOut(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn test_runner_run_days() {
+        let reports = run_days(
+            &[1, 3],
+            |day| match day {
+                1 => Ok("1\n2\n3\n4".to_string()),
+                _ => Err("missing input".to_string()),
+            },
+            TIMEOUT,
+        );
+
+        let outcomes: Vec<Outcome> = reports.into_iter().map(|x| x.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                Outcome::Answer("3".to_string()),
+                Outcome::Answer("1".to_string()),
+                Outcome::Failed("missing input".to_string()),
+                Outcome::Failed("missing input".to_string()),
+            ]
+        );
+    }
+}
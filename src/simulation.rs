@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::{day06, day11, day13};
+
+/// Characters used to render a frame in the terminal, from dim to bright.
+const SHADES: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// A single picture of a simulation. Each cell holds an intensity value,
+/// where `0` is the background and `255` is the brightest.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u8>,
+    pub caption: String,
+}
+
+impl Frame {
+    pub fn cell_at(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * self.width + x]
+    }
+
+    /// Encodes the frame as a binary PGM (grayscale) image, which doesn't
+    /// need any extra crates and most image viewers can open.
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut buffer = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        buffer.extend_from_slice(&self.cells);
+        buffer
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = format!("{}\n", self.caption);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let shade = self.cell_at(x, y) as usize * (SHADES.len() - 1) / 255;
+                buffer.push(SHADES[shade]);
+            }
+            buffer.push('\n');
+        }
+        write!(f, "{}", buffer)
+    }
+}
+
+pub trait Simulation {
+    /// Advances the simulation one step. Returns `false` when there is
+    /// nothing left to simulate.
+    fn step(&mut self) -> bool;
+    fn snapshot(&self) -> Frame;
+}
+
+/// Runs a simulation for `max_steps` (or until it finishes), calling
+/// `on_frame` with the initial state and after every step.
+pub fn run<F>(sim: &mut dyn Simulation, max_steps: usize, mut on_frame: F) -> usize
+where
+    F: FnMut(usize, &Frame),
+{
+    on_frame(0, &sim.snapshot());
+
+    let mut steps = 0;
+    while steps < max_steps && sim.step() {
+        steps += 1;
+        on_frame(steps, &sim.snapshot());
+    }
+
+    steps
+}
+
+pub fn for_day(day: u8, input: &str) -> Option<Box<dyn Simulation>> {
+    match day {
+        6 => Some(Box::new(day06::School::new(&day06::parse_input(input)))),
+        11 => Some(Box::new(day11::OctopusSim::new(day11::parse_input(input)))),
+        13 => Some(Box::new(day13::Folding::new(day13::parse_input(input)))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_display() {
+        let frame = Frame {
+            width: 3,
+            height: 2,
+            cells: vec![0, 128, 255, 255, 0, 0],
+            caption: "test".to_string(),
+        };
+        assert_eq!(format!("{}", frame), "test\n =@\n@  \n");
+    }
+
+    #[test]
+    fn test_frame_to_pgm() {
+        let frame = Frame {
+            width: 2,
+            height: 1,
+            cells: vec![0, 255],
+            caption: "".to_string(),
+        };
+        assert_eq!(frame.to_pgm(), b"P5\n2 1\n255\n\x00\xff".to_vec());
+    }
+
+    #[test]
+    fn test_simulation_run() {
+        let mut sim = for_day(13, "0,0\n0,2\n\nfold along y=1").unwrap();
+        let mut captions = vec![];
+        let steps = run(sim.as_mut(), 10, |step, frame| {
+            captions.push(format!("{}: {}x{}", step, frame.width, frame.height))
+        });
+
+        assert_eq!(steps, 1);
+        assert_eq!(captions, vec!["0: 1x3", "1: 1x1"]);
+    }
+}
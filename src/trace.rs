@@ -0,0 +1,142 @@
+use lazy_static::lazy_static;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum EventKind {
+    NodeExpanded,
+    StateCached,
+    Iteration,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Event {
+    pub day: u8,
+    pub kind: EventKind,
+}
+
+pub trait Subscriber: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+lazy_static! {
+    static ref GLOBAL: RwLock<Option<Arc<dyn Subscriber>>> = RwLock::new(None);
+}
+
+thread_local! {
+    static LOCAL: RefCell<Option<Arc<dyn Subscriber>>> = RefCell::new(None);
+}
+
+/// Sets the subscriber that receives the events of every thread (solvers
+/// launched by the runner live in their own threads).
+pub fn set_global_subscriber(subscriber: Arc<dyn Subscriber>) {
+    *GLOBAL.write().unwrap() = Some(subscriber);
+}
+
+/// Puts the previous local subscriber back when dropped, even if the
+/// scope it guards panics.
+struct LocalScope {
+    previous: Option<Arc<dyn Subscriber>>,
+}
+
+impl Drop for LocalScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        LOCAL.with(|local| local.replace(previous));
+    }
+}
+
+/// Runs `f` with a subscriber that only listens to the current thread. It
+/// takes precedence over the global one, so tests running in parallel
+/// don't count each other's events.
+pub fn with_subscriber<T, F: FnOnce() -> T>(subscriber: Arc<dyn Subscriber>, f: F) -> T {
+    let _scope = LocalScope {
+        previous: LOCAL.with(|local| local.replace(Some(subscriber))),
+    };
+    f()
+}
+
+#[cfg(feature = "trace")]
+pub fn emit(day: u8, kind: EventKind) {
+    let event = Event { day, kind };
+    let handled = LOCAL.with(|local| match &*local.borrow() {
+        Some(subscriber) => {
+            subscriber.on_event(&event);
+            true
+        }
+        None => false,
+    });
+
+    if !handled {
+        if let Some(subscriber) = &*GLOBAL.read().unwrap() {
+            subscriber.on_event(&event);
+        }
+    }
+}
+
+#[cfg(not(feature = "trace"))]
+#[inline(always)]
+pub fn emit(_day: u8, _kind: EventKind) {}
+
+#[derive(Debug, Default)]
+pub struct Counter {
+    counts: Mutex<HashMap<(u8, EventKind), u64>>,
+}
+
+impl Counter {
+    pub fn count(&self, day: u8, kind: EventKind) -> u64 {
+        *self.counts.lock().unwrap().get(&(day, kind)).unwrap_or(&0)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.lock().unwrap().values().sum()
+    }
+}
+
+impl Subscriber for Counter {
+    fn on_event(&self, event: &Event) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry((event.day, event.kind))
+            .or_insert(0) += 1;
+    }
+}
+
+#[cfg(all(test, feature = "trace"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_counter() {
+        let counter = Arc::new(Counter::default());
+
+        with_subscriber(counter.clone(), || {
+            emit(1, EventKind::Iteration);
+            emit(1, EventKind::Iteration);
+            emit(2, EventKind::StateCached);
+        });
+        emit(1, EventKind::Iteration); // outside the scope, not counted
+
+        assert_eq!(counter.count(1, EventKind::Iteration), 2);
+        assert_eq!(counter.count(2, EventKind::StateCached), 1);
+        assert_eq!(counter.count(2, EventKind::NodeExpanded), 0);
+        assert_eq!(counter.total(), 3);
+    }
+
+    #[test]
+    fn test_trace_panicking_scope() {
+        let counter = Arc::new(Counter::default());
+
+        let result = std::panic::catch_unwind(|| {
+            with_subscriber(counter.clone(), || panic!("solver failed"));
+        });
+        assert!(result.is_err());
+        emit(1, EventKind::Iteration); // the subscriber is gone after the panic
+
+        assert_eq!(counter.total(), 0);
+    }
+}
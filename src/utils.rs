@@ -28,7 +28,7 @@ impl<T: Clone> Grid<T> {
         }
 
         let i = self.index_for(x as usize, y as usize);
-        Some(self.cells[i as usize].clone())
+        Some(self.cells[i ].clone())
     }
 
     pub fn full_cell(&self, x: usize, y: usize) -> (T, (usize, usize)) {